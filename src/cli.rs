@@ -11,26 +11,79 @@ struct Cli {
     #[arg(long)]
     pub config: Option<PathBuf>,
 
+    /// Name of the volume to use when multiple volumes are configured
+    #[arg(long)]
+    pub volume: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<SubCommand>,
 }
 
-#[derive(Copy, Clone, Default, Display, Subcommand)]
+#[derive(Copy, Clone, Display, Subcommand)]
 pub enum SubCommand {
     /// Unlock and mount a LUKS encrypted partition
-    #[default]
-    Mount,
+    Mount {
+        /// Unlock and mount every configured volume in turn
+        #[arg(long)]
+        all: bool,
+    },
+    /// Unmount and lock a LUKS encrypted partition
+    Unmount,
+    /// Report the state of each configured volume
+    Status,
     /// Check the key
     Validate,
     /// Save the TPM component of the passphrase in TPM
     SetTpm,
+    /// Reseal the TPM component of the passphrase after a firmware or Secure Boot change
+    RotateTpm,
+    /// Manage persistent TPM objects
+    Tpm {
+        #[command(subcommand)]
+        command: TpmCommand,
+    },
     /// Add the passphrase to LUKS
-    SetLuks,
+    SetLuks {
+        /// Keyslot to add the passphrase to, defaults to the first free keyslot
+        #[arg(long)]
+        key_slot: Option<u32>,
+    },
+    /// Remove the keyslot of the passphrase from LUKS
+    RemoveLuks,
+    /// Replace the keyslot of the passphrase after a key source changes
+    RotateLuks,
+    /// Bind the Tang component of the passphrase to a Tang server
+    SetTang,
+}
+
+#[derive(Copy, Clone, Display, Subcommand)]
+pub enum TpmCommand {
+    /// List persistent TPM handles and the volumes that reference them
+    List,
+    /// Evict a persistent TPM object
+    Evict {
+        /// Handle to evict, defaults to the `tpm_handle` of the selected volume
+        #[arg(long)]
+        handle: Option<PersistentHandle>,
+        /// Evict even if a configured volume depends on the handle
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+impl Default for SubCommand {
+    fn default() -> Self {
+        Self::Mount { all: false }
+    }
 }
 
 #[must_use]
 pub fn cli() -> ExitCode {
-    if let Err(e) = cli_internal() {
+    let result = cli_internal();
+    if let Err(report) = remove_temp_dir() {
+        warn!("Unable to remove TPM temp directory:\n{report:?}");
+    }
+    if let Err(e) = result {
         print_error("Unable to continue");
         eprintln!("\n{e}");
         ExitCode::FAILURE
@@ -42,15 +95,64 @@ pub fn cli() -> ExitCode {
 fn cli_internal() -> Result<(), AnyReport> {
     let cli = Cli::parse();
     init_elapsed_logger();
-    let options = Options::read_options(cli.config)?;
     let command = cli.command.unwrap_or_default();
-    if options.no_header != Some(true) {
-        print_header(&options, command);
-    }
     match command {
-        SubCommand::Mount => mount_command(options),
-        SubCommand::Validate => validate_command(options),
-        SubCommand::SetTpm => set_tpm_command(options),
-        SubCommand::SetLuks => set_luks_command(options),
+        SubCommand::Mount { all: false } => mount_command(cli.read_options(command)?),
+        SubCommand::Mount { all: true } => mount_all_command(cli.read_volumes()?),
+        SubCommand::Unmount => unmount_command(cli.read_options(command)?),
+        SubCommand::Status => {
+            status_command(&cli.read_volumes()?);
+            Ok(())
+        }
+        SubCommand::Validate => validate_command(cli.read_options(command)?),
+        SubCommand::SetTpm => set_tpm_command(cli.read_options(command)?),
+        SubCommand::RotateTpm => rotate_tpm_command(cli.read_options(command)?),
+        SubCommand::Tpm {
+            command: TpmCommand::List,
+        } => {
+            let volumes = Options::read_all(cli.config.clone()).unwrap_or_else(|report| {
+                warn!("Unable to read options so volumes are not listed:\n{report:?}");
+                Vec::new()
+            });
+            tpm_list_command(volumes)
+        }
+        SubCommand::Tpm {
+            command: TpmCommand::Evict { handle, force },
+        } => {
+            let handle = match handle {
+                Some(handle) => handle,
+                None => cli
+                    .read_options(command)?
+                    .get_tpm_handle()
+                    .ok_or_else(|| Report::new(TpmHandleRequired))?,
+            };
+            tpm_evict_command(Options::read_all(cli.config.clone())?, handle, force)
+        }
+        SubCommand::SetLuks { key_slot } => set_luks_command(cli.read_options(command)?, key_slot),
+        SubCommand::RemoveLuks => remove_luks_command(cli.read_options(command)?),
+        SubCommand::RotateLuks => rotate_luks_command(cli.read_options(command)?),
+        SubCommand::SetTang => set_tang_command(cli.read_options(command)?),
+    }
+}
+
+impl Cli {
+    /// Read the options of the selected volume and print the header.
+    fn read_options(&self, command: SubCommand) -> Result<Options, Report<OptionsError>> {
+        let options = Options::read_options(self.config.clone(), self.volume.as_deref())?;
+        if options.no_header != Some(true) {
+            print_header(&options, command);
+        }
+        Ok(options)
+    }
+
+    /// Read the options of the selected volume, or of every volume if none is selected.
+    fn read_volumes(&self) -> Result<Vec<Options>, Report<OptionsError>> {
+        match &self.volume {
+            Some(volume) => Ok(vec![Options::read_options(
+                self.config.clone(),
+                Some(volume),
+            )?]),
+            None => Options::read_all(self.config.clone()),
+        }
     }
 }
@@ -1,9 +1,11 @@
 mod attach_key_value;
 mod attach_path;
 mod output_ok_or_report;
+mod wait_with_secret;
 mod write_to_stdin;
 
 pub use attach_key_value::*;
 pub use attach_path::*;
 pub use output_ok_or_report::*;
+pub use wait_with_secret::*;
 pub use write_to_stdin::*;
@@ -0,0 +1,21 @@
+use crate::prelude::*;
+use std::io::Error;
+use std::process::{Child, Output};
+
+pub trait WaitWithSecret {
+    /// Wait for the child to exit, reading stdout into a [`Secret`].
+    ///
+    /// The stdout of the returned output is empty.
+    fn wait_with_secret(self) -> Result<(Secret, Output), Error>;
+}
+
+impl WaitWithSecret for Child {
+    fn wait_with_secret(mut self) -> Result<(Secret, Output), Error> {
+        let secret = match self.stdout.take() {
+            Some(stdout) => Secret::read_from(stdout, 0)?,
+            None => Secret::new(Vec::new()),
+        };
+        let output = self.wait_with_output()?;
+        Ok((secret, output))
+    }
+}
@@ -2,20 +2,20 @@ use std::io::Write;
 use std::process::Child;
 
 pub trait WriteToStdin {
-    fn write_to_stdin(self, input: &str) -> Self;
+    fn write_to_stdin(self, input: &[u8]) -> Self;
 }
 
 impl WriteToStdin for Child {
-    fn write_to_stdin(mut self, input: &str) -> Self {
+    fn write_to_stdin(mut self, input: &[u8]) -> Self {
         write_to_stdin(&mut self, input);
         self
     }
 }
 
-fn write_to_stdin(child: &mut Child, input: &str) {
+fn write_to_stdin(child: &mut Child, input: &[u8]) {
     if let Some(mut stdin) = child.stdin.take() {
         stdin
-            .write_all(input.as_bytes())
+            .write_all(input)
             .expect("Should be able to write to stdin");
     }
 }
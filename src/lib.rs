@@ -2,5 +2,6 @@ mod cli;
 mod extensions;
 mod luks;
 pub mod prelude;
+mod tang;
 pub mod tpm;
 mod utils;
@@ -1,31 +1,131 @@
 use crate::prelude::*;
+use nix::fcntl::{FcntlArg, FdFlag, fcntl};
 use rpassword::prompt_password;
+use std::io::{Write, pipe};
+use std::os::fd::AsRawFd;
 use std::process::Stdio;
 
-pub fn add_key(options: &Options) -> Result<(), Report<KeyError>> {
+/// Add the key of the volume to `key_slot`, or the first free keyslot.
+///
+/// Returns the keyslot the key was added to.
+pub fn add_key(options: &Options, key_slot: Option<u32>) -> Result<u32, Report<KeyError>> {
+    if let Some(threshold) = options.key_threshold {
+        return add_threshold_key(options, threshold, key_slot);
+    }
+    let backend = luks_backend();
     let key = get_key(options)?;
-    if check_key(options, &key).is_ok() {
+    if backend.check_key(options, &key).is_ok() {
         return Err(Report::new(KeyError::Exists));
     }
-    add_key_internal(options, &key)?;
-    Ok(())
+    debug!("Key is {} bytes", key.len());
+    let existing = prompt_existing_passphrase()?;
+    backend.add_key(options, &existing, &key, key_slot)
+}
+
+/// Split a new random volume secret between every key source and add it as a LUKS key.
+///
+/// The shares file is staged before the key is added and only replaces the previous shares
+/// file once the key has been checked, so a failure leaves any previous shares file usable.
+/// If the key was added but can't be checked or recorded, its keyslot is removed again so
+/// no keyslot is left that nothing can unlock.
+fn add_threshold_key(
+    options: &Options,
+    threshold: u8,
+    key_slot: Option<u32>,
+) -> Result<u32, Report<KeyError>> {
+    let backend = luks_backend();
+    let (key, shares) = split_threshold_key(options, threshold)?;
+    let path = options.get_key_shares_path();
+    if path.exists() {
+        warn!(path = %path.display(), "Replacing shares file, the previous LUKS key is not removed");
+    }
+    let staged = stage_key_shares(&path, &shares).change_context(KeyError::Shares)?;
+    let slot = prompt_existing_passphrase()
+        .and_then(|existing| backend.add_key(options, &existing, &key, key_slot))
+        .inspect_err(|_| discard_key_shares(&staged))?;
+    backend
+        .check_key(options, &key)
+        .and_then(|()| commit_key_shares(&staged, &path).change_context(KeyError::Shares))
+        .inspect_err(|_| {
+            remove_added_key_slot(options, slot);
+            discard_key_shares(&staged);
+        })
+        .attach(format!("Keyslot {slot} was added but can't be used"))?;
+    Ok(slot)
+}
+
+/// Remove a keyslot added by this run that can't be used.
+///
+/// Failing to remove it is only logged so the error that made it unusable is reported.
+pub fn remove_added_key_slot(options: &Options, slot: u32) {
+    match luks_backend().remove_key_slot(options, slot) {
+        Ok(()) => warn!("Removed keyslot {slot} that was added but can't be used"),
+        Err(report) => warn!(
+            "Unable to remove keyslot {slot}, remove it with `cryptsetup luksKillSlot`:\n{report:?}"
+        ),
+    }
 }
 
-fn add_key_internal(options: &Options, key: &str) -> Result<(), Report<KeyError>> {
-    debug!("Key is {} characters", key.len());
-    let existing = prompt_password("Enter existing passphrase: ")
+/// Split a new random volume secret between every key source.
+///
+/// Every source must be available to enrol.
+pub fn split_threshold_key(
+    options: &Options,
+    threshold: u8,
+) -> Result<(Secret, KeyShares), Report<KeyError>> {
+    let sources = get_sources(options);
+    if sources.is_empty() {
+        return Err(Report::new(KeyError::Required));
+    }
+    let components = sources
+        .iter()
+        .map(|&source| get_component(options, source).map(|component| (source, component)))
+        .collect::<Result<Vec<_>, _>>()?;
+    KeyShares::split(threshold, &components).change_context(KeyError::Shares)
+}
+
+pub fn prompt_existing_passphrase() -> Result<Secret, Report<KeyError>> {
+    prompt_password("Enter existing passphrase: ")
+        .map(Secret::from)
         .change_context(KeyError::Add)
-        .attach("Failed to read existing passphrase")?;
-    let input = [&existing, key, key].join("\n");
-    Command::new("cryptsetup")
+        .attach("Failed to read existing passphrase")
+}
+
+/// Add `key` to `key_slot`, or the first free keyslot, authorized by an `existing`
+/// passphrase.
+///
+/// The new key is written to stdin as a key file so binary keys aren't cut short at a
+/// newline, and the existing passphrase is read from an inherited pipe.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-luksAddKey.8.html>
+pub fn add_key_with_passphrase(
+    options: &Options,
+    existing: &Secret,
+    key: &Secret,
+    key_slot: Option<u32>,
+) -> Result<(), Report<KeyError>> {
+    let (reader, mut writer) = pipe().change_context(KeyError::Add)?;
+    writer
+        .write_all(existing.expose())
+        .change_context(KeyError::Add)?;
+    drop(writer);
+    fcntl(&reader, FcntlArg::F_SETFD(FdFlag::empty())).change_context(KeyError::Add)?;
+    let mut command = Command::new("cryptsetup");
+    command
         .arg("luksAddKey")
+        .arg(format!("--key-file=/dev/fd/{}", reader.as_raw_fd()));
+    if let Some(slot) = key_slot {
+        command.arg(format!("--key-slot={slot}"));
+    }
+    command
         .arg(options.partition_path.display().to_string())
+        .arg("-") // Read new key file from stdin
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
         .expect("should be able to spawn `cryptsetup luksAddKey`")
-        .write_to_stdin(&input)
+        .write_to_stdin(key.expose())
         .wait_with_output()
         .expect("should be able to wait on `cryptsetup luksAddKey`")
         .ok_or(KeyError::Add)
@@ -1,19 +1,26 @@
 use crate::prelude::*;
 
 pub fn check_if_mounted(options: &Options) -> Result<(), Report<AlreadyMounted>> {
-    let response = Command::new("findmnt")
-        .arg("--noheadings")
-        .arg(options.mount_path.display().to_string())
-        .output()
-        .expect("should be able to execute `findmnt`")
-        .to_response();
-    if response.status.success() {
+    if is_mounted(options) {
         Err(Report::new(AlreadyMounted).attach_path(&options.mount_path))
     } else {
         Ok(())
     }
 }
 
+/// Check if anything is mounted at the mount path.
+///
+/// - <https://man7.org/linux/man-pages/man8/findmnt.8.html>
+pub fn is_mounted(options: &Options) -> bool {
+    Command::new("findmnt")
+        .arg("--noheadings")
+        .arg(options.mount_path.display().to_string())
+        .output()
+        .expect("should be able to execute `findmnt`")
+        .status
+        .success()
+}
+
 #[derive(Clone, Copy, Debug, Error, PartialEq)]
 #[error("Partition is already mounted")]
 pub struct AlreadyMounted;
@@ -25,7 +32,7 @@ mod tests {
     #[test]
     fn _check_if_mounted() {
         // Arrange
-        let options = Options::read_options(None).expect("Should be able to read options");
+        let options = Options::read_options(None, None).expect("Should be able to read options");
 
         // Act
         let result = check_if_mounted(&options);
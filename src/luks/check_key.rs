@@ -3,8 +3,11 @@ use crate::prelude::*;
 use error_stack::Report;
 use std::process::{Command, Stdio};
 
-pub fn check_key(options: &Options, key: &str) -> Result<(), Report<KeyError>> {
-    Command::new("cryptsetup")
+/// Check the key unlocks the LUKS partition without activating it.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-open.8.html>
+pub fn check_key(options: &Options, key: &Secret) -> Result<(), Report<KeyError>> {
+    let output = Command::new("cryptsetup")
         .arg("luksOpen")
         .arg("--test-passphrase")
         .arg("--key-file=-")
@@ -14,8 +17,70 @@ pub fn check_key(options: &Options, key: &str) -> Result<(), Report<KeyError>> {
         .stderr(Stdio::piped())
         .spawn()
         .expect("should be able to spawn `cryptsetup luksOpen --test-passphrase`")
-        .write_to_stdin(key)
+        .write_to_stdin(key.expose())
         .wait_with_output()
-        .expect("should be able to wait on `cryptsetup luksOpen --test-passphrase`")
-        .ok_or(KeyError::InvalidKey)
+        .expect("should be able to wait on `cryptsetup luksOpen --test-passphrase`");
+    if output.status.success() {
+        return Ok(());
+    }
+    let error = KeyError::from_exit_status(output.status, KeyError::InvalidKey);
+    Err(Report::new(error).attach_response(output.to_response()))
+}
+
+/// Find the keyslot that `key` unlocks.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-open.8.html>
+pub fn find_key_slot(options: &Options, key: &Secret) -> Result<u32, Report<KeyError>> {
+    let output = Command::new("cryptsetup")
+        .arg("luksOpen")
+        .arg("--test-passphrase")
+        .arg("--verbose")
+        .arg("--key-file=-")
+        .arg(options.partition_path.display().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should be able to spawn `cryptsetup luksOpen --test-passphrase`")
+        .write_to_stdin(key.expose())
+        .wait_with_output()
+        .expect("should be able to wait on `cryptsetup luksOpen --test-passphrase`");
+    if !output.status.success() {
+        let error = KeyError::from_exit_status(output.status, KeyError::InvalidKey);
+        return Err(Report::new(error).attach_response(output.to_response()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    parse_key_slot(&stdout)
+        .ok_or_else(|| Report::new(KeyError::KeySlot).attach_response(output.to_response()))
+}
+
+/// Parse the keyslot from the verbose output of `cryptsetup luksOpen --test-passphrase`.
+///
+/// Example: `Key slot 1 unlocked.`
+fn parse_key_slot(output: &str) -> Option<u32> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Key slot ")?
+            .strip_suffix(" unlocked.")?
+            .parse()
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _parse_key_slot() {
+        // Arrange
+        let output = "Key slot 3 unlocked.\nCommand successful.\n";
+
+        // Act
+        let slot = parse_key_slot(output);
+
+        // Assert
+        assert_eq!(slot, Some(3));
+        assert_eq!(parse_key_slot("Command successful."), None);
+    }
 }
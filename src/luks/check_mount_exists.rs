@@ -19,7 +19,7 @@ mod tests {
     #[test]
     fn _check_mount_exists() {
         // Arrange
-        let options = Options::read_options(None).expect("Should be able to read options");
+        let options = Options::read_options(None, None).expect("Should be able to read options");
 
         // Act
         let result = check_mount_exists(&options);
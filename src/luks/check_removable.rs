@@ -0,0 +1,223 @@
+use crate::prelude::*;
+use nix::sys::stat::{major, minor};
+use std::fs::{File, canonicalize, read_to_string};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+/// Mount table of the current process.
+///
+/// - <https://man7.org/linux/man-pages/man5/proc_pid_mountinfo.5.html>
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// Directory of block devices by `major:minor` device number.
+const SYSFS_DEV_BLOCK: &str = "/sys/dev/block";
+
+/// Directory of block devices by name.
+const SYSFS_CLASS_BLOCK: &str = "/sys/class/block";
+
+/// Entry of the mount table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MountInfo {
+    /// Device number as `major:minor`
+    pub device: String,
+    /// Path the filesystem is mounted at
+    pub mount_point: PathBuf,
+}
+
+/// Check the open `file` is on a filesystem backed by a removable disk.
+///
+/// The device is taken from the open descriptor rather than by resolving `path` again, so
+/// the file that is checked is the file that is read. `path` is only used in errors.
+/// If `file` is itself a block device then the device is checked instead.
+pub fn check_removable(file: &File, path: &Path) -> Result<(), Report<RemovableError>> {
+    let metadata = file
+        .metadata()
+        .change_context(RemovableError::Resolve)
+        .attach_path(path)?;
+    if metadata.file_type().is_block_device() {
+        let sysfs = Path::new(SYSFS_DEV_BLOCK).join(get_device_number(metadata.rdev()));
+        return check_sysfs_removable(&sysfs).attach_path(path);
+    }
+    let device = get_device_number(metadata.dev());
+    let mountinfo = read_to_string(MOUNTINFO_PATH)
+        .change_context(RemovableError::MountInfo)
+        .attach_path(Path::new(MOUNTINFO_PATH))?;
+    let mount = find_mount(&mountinfo, &device)
+        .ok_or_else(|| Report::new(RemovableError::MountInfo))
+        .attach_key_value("Device", &device)
+        .attach_path(path)?;
+    let sysfs = Path::new(SYSFS_DEV_BLOCK).join(&mount.device);
+    check_sysfs_removable(&sysfs)
+        .attach_key_value("Mount", &mount.mount_point.display().to_string())
+        .attach_path(path)
+}
+
+/// Check the block device at `path` is a removable disk or a partition of one.
+pub fn check_device_removable(path: &Path) -> Result<(), Report<RemovableError>> {
+    let device = canonicalize(path)
+        .change_context(RemovableError::Resolve)
+        .attach_path(path)?;
+    let name = device
+        .file_name()
+        .ok_or_else(|| Report::new(RemovableError::Resolve))
+        .attach_path(&device)?;
+    check_sysfs_removable(&Path::new(SYSFS_CLASS_BLOCK).join(name)).attach_path(&device)
+}
+
+/// Read the `removable` attribute of the disk of a sysfs block device.
+fn check_sysfs_removable(sysfs: &Path) -> Result<(), Report<RemovableError>> {
+    let sysfs = canonicalize(sysfs)
+        .change_context(RemovableError::Sysfs)
+        .attach_path(sysfs)?;
+    let disk = get_disk(&sysfs);
+    let path = disk.join("removable");
+    let removable = read_to_string(&path)
+        .change_context(RemovableError::Sysfs)
+        .attach_path(&path)?;
+    if removable.trim() != "1" {
+        return Err(Report::new(RemovableError::NotRemovable).attach_path(disk));
+    }
+    Ok(())
+}
+
+/// Get the sysfs directory of the disk that a partition belongs to.
+///
+/// Partitions have a `partition` attribute and are a subdirectory of their disk.
+fn get_disk(sysfs: &Path) -> &Path {
+    if sysfs.join("partition").exists() {
+        sysfs.parent().unwrap_or(sysfs)
+    } else {
+        sysfs
+    }
+}
+
+/// Find a mount of the filesystem with the `major:minor` device number `device`.
+///
+/// If the filesystem is mounted more than once then the last mount is used.
+#[must_use]
+pub fn find_mount(mountinfo: &str, device: &str) -> Option<MountInfo> {
+    mountinfo
+        .lines()
+        .filter_map(parse_mountinfo_line)
+        .rfind(|mount| mount.device == device)
+}
+
+/// Format a device number as `major:minor`, as used by mountinfo and sysfs.
+fn get_device_number(device: u64) -> String {
+    format!("{}:{}", major(device), minor(device))
+}
+
+fn parse_mountinfo_line(line: &str) -> Option<MountInfo> {
+    let mut fields = line.split_whitespace();
+    let device = fields.nth(2)?.to_owned();
+    let mount_point = PathBuf::from(unescape_mountinfo(fields.nth(1)?));
+    Some(MountInfo {
+        device,
+        mount_point,
+    })
+}
+
+/// Decode the octal escapes used for whitespace and backslashes in mount points.
+fn unescape_mountinfo(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find('\\') {
+        let (before, after) = rest.split_at(index);
+        output.push_str(before);
+        let decoded = after
+            .get(1..4)
+            .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+        if let Some(byte) = decoded {
+            output.push(char::from(byte));
+            rest = after.get(4..).unwrap_or_default();
+        } else {
+            output.push('\\');
+            rest = after.get(1..).unwrap_or_default();
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum RemovableError {
+    #[error("Unable to resolve path")]
+    Resolve,
+    #[error("Unable to find the mount in `/proc/self/mountinfo`")]
+    MountInfo,
+    #[error("Unable to read the block device from sysfs")]
+    Sysfs,
+    #[error("Disk is not removable")]
+    NotRemovable,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::stat::makedev;
+
+    const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+25 22 0:22 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+41 22 259:1 / /boot rw,relatime shared:29 - vfat /dev/nvme0n1p1 rw
+97 22 8:17 / /media/root/MY\\040KEYS rw,nosuid,nodev,relatime shared:51 - vfat /dev/sdb1 rw
+";
+
+    #[test]
+    fn find_mount_removable() {
+        // Arrange
+        // Act
+        let mount = find_mount(MOUNTINFO, "8:17");
+
+        // Assert
+        assert_eq!(
+            mount,
+            Some(MountInfo {
+                device: "8:17".to_owned(),
+                mount_point: PathBuf::from("/media/root/MY KEYS"),
+            })
+        );
+    }
+
+    #[test]
+    fn find_mount_root() {
+        // Arrange
+        // Act
+        let mount = find_mount(MOUNTINFO, "259:2");
+
+        // Assert
+        let mount = mount.expect("Should find mount");
+        assert_eq!(mount.mount_point, PathBuf::from("/"));
+    }
+
+    #[test]
+    fn find_mount_missing() {
+        // Arrange
+        // Act
+        let mount = find_mount(MOUNTINFO, "8:1");
+
+        // Assert
+        assert_eq!(mount, None);
+    }
+
+    #[test]
+    fn _get_device_number() {
+        // Arrange
+        let device = makedev(259, 2);
+
+        // Act
+        let output = get_device_number(device);
+
+        // Assert
+        assert_eq!(output, "259:2");
+    }
+
+    #[test]
+    fn _unescape_mountinfo() {
+        // Arrange
+        // Act
+        let output = unescape_mountinfo("/a\\040b\\134c\\d");
+
+        // Assert
+        assert_eq!(output, "/a b\\c\\d");
+    }
+}
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+use std::env::var_os;
+
+/// Environment variable set by systemd to the directory of the service's credentials.
+///
+/// - <https://systemd.io/CREDENTIALS/>
+const CREDENTIALS_DIRECTORY: &str = "CREDENTIALS_DIRECTORY";
+
+/// Read a systemd credential passed with `LoadCredential=` or `LoadCredentialEncrypted=`.
+///
+/// - <https://www.freedesktop.org/software/systemd/man/latest/systemd.exec.html#Credentials>
+pub fn read_credential(name: &str) -> Result<Secret, Report<KeyError>> {
+    let dir = var_os(CREDENTIALS_DIRECTORY)
+        .ok_or_else(|| Report::new(KeyError::CredentialsDirectory))
+        .attach_key_value("Credential", name)?;
+    read_credential_from(Path::new(&dir), name)
+}
+
+/// Check a systemd credential has been passed without reading it.
+#[must_use]
+pub fn has_credential(name: &str) -> bool {
+    var_os(CREDENTIALS_DIRECTORY).is_some_and(|dir| Path::new(&dir).join(name).is_file())
+}
+
+fn read_credential_from(dir: &Path, name: &str) -> Result<Secret, Report<KeyError>> {
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return Err(Report::new(KeyError::Credential)
+            .attach_key_value("Credential", name)
+            .attach("Credential name must be a file name"));
+    }
+    let path = dir.join(name);
+    let key = read_key_file(&path, None, None)
+        .change_context(KeyError::Credential)
+        .attach_key_value("Credential", name)
+        .attach_path(&path)?;
+    Ok(key.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[test]
+    fn _read_credential_from() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        write(dir.join("mount-luks.key"), "key\n").expect("Should be able to write credential");
+
+        // Act
+        let result = read_credential_from(&dir, "mount-luks.key");
+
+        // Assert
+        let key = result.expect("Should be able to read credential");
+        assert_eq!(key.expose(), b"key");
+    }
+
+    #[test]
+    fn read_credential_from_path() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+
+        // Act
+        let result = read_credential_from(&dir, "../mount-luks.key");
+
+        // Assert
+        let report = result.expect_err("Should reject path");
+        assert_eq!(report.current_context(), &KeyError::Credential);
+    }
+}
@@ -1,80 +1,188 @@
 use crate::prelude::*;
-use rpassword::prompt_password;
-use std::fs::read_to_string;
+use std::process::ExitStatus;
 
-/// Get the key by concatenating all sources of key material.
-/// - File
-/// - TPM
+/// Get the key by combining all sources of key material.
+/// - File, optionally on a removable key device
+/// - TPM, optionally cached in the user keyring
 /// - Prompt
-pub fn get_key(options: &Options) -> Result<String, Report<KeyError>> {
-    let mut components = Vec::new();
-    if let Some(path) = &options.key_path {
-        trace!(path = %path.display(), "Reading key from file");
-        let key = read_to_string(path)
-            .change_context(KeyError::KeyFile)
-            .attach_path(path)?
-            .trim()
-            .to_owned();
-        if key.is_empty() {
-            warn!(path = %path.display(), "Key file is empty");
-        }
-        components.push(key);
+/// - Keyring
+/// - systemd credential
+/// - Tang server
+///
+/// The components are combined according to the `key_derivation` option, unless
+/// `key_threshold` is set in which case any `key_threshold` of them recover the key from the
+/// shares file.
+///
+/// If the options file doesn't set any key source then they are read from the LUKS2 token
+/// written by `set-luks`.
+///
+/// Every component is held in a [`Secret`] so it's zeroed once the key has been derived.
+pub fn get_key(options: &Options) -> Result<Secret, Report<KeyError>> {
+    let mut sources = get_sources(options);
+    let token_options;
+    let options = if sources.is_empty() {
+        token_options = apply_luks_token(options).change_context(KeyError::Token)?;
+        sources = get_sources(&token_options);
+        &token_options
+    } else {
+        options
+    };
+    if sources.is_empty() {
+        return Err(Report::new(KeyError::Required));
     }
-    if let Some(handle) = &options.tpm_handle {
-        trace!(%handle, "Reading key from TPM");
-        let key = unseal_persistent_object(handle)
-            .change_context(KeyError::Tpm)
-            .attach_key_value("Handle", &handle.to_string())?;
-        if key.is_empty() {
-            warn!(%handle, "TPM key value is empty");
-        }
-        components.push(key);
+    if options.key_threshold.is_some() {
+        return get_threshold_key(options, &sources);
     }
-    if options.key_prompt == Some(true) {
-        trace!("Reading key from prompt");
-        let key = prompt_password("Enter interactive key component: ")
-            .change_context(KeyError::Prompt)?
-            .trim()
-            .to_owned();
-        if key.is_empty() {
-            warn!("Prompt value is empty");
+    let components = sources
+        .iter()
+        .map(|&source| get_component(options, source))
+        .collect::<Result<Vec<_>, _>>()?;
+    let derivation = options.key_derivation.unwrap_or_default();
+    trace!(%derivation, "Deriving key from {} components", components.len());
+    Ok(derive_key(derivation, &components))
+}
+
+/// Read the key components of every source until the shares recover a key that unlocks the
+/// partition.
+///
+/// The shares file can't tell whether a share was unwrapped with the right component, so
+/// each recovered key is checked against LUKS. Once `key_threshold` shares have been read,
+/// every combination with the latest share is tried before the next source is read.
+///
+/// A source that fails is skipped with a warning so the volume can still be unlocked
+/// without it.
+fn get_threshold_key(options: &Options, sources: &[KeySource]) -> Result<Secret, Report<KeyError>> {
+    let path = options.get_key_shares_path();
+    let key_shares = read_key_shares(&path).change_context(KeyError::Shares)?;
+    let threshold = usize::from(key_shares.threshold);
+    let backend = luks_backend();
+    let mut shares = Vec::new();
+    for &source in sources {
+        if !key_shares.shares.contains_key(&source) {
+            warn!(%source, "Key source does not have a share, run `set-luks` again");
+            continue;
+        }
+        let share = get_component(options, source).and_then(|component| {
+            key_shares
+                .unwrap_share(source, &component)
+                .change_context(KeyError::Shares)
+        });
+        match share {
+            Ok(share) => shares.push(share),
+            Err(report) => {
+                warn!(%source, "Unable to read key share:\n{report:?}");
+                continue;
+            }
+        }
+        let Some((latest, previous)) = shares.split_last() else {
+            continue;
+        };
+        for mut combination in get_combinations(previous, threshold.saturating_sub(1)) {
+            combination.push(latest);
+            trace!(
+                "Recovering key from {} of {threshold} shares",
+                combination.len()
+            );
+            let key = key_shares
+                .recover(&combination)
+                .change_context(KeyError::Shares)
+                .attach_path(&path)?;
+            match backend.check_key(options, &key) {
+                Ok(()) => return Ok(key),
+                Err(report) if report.current_context() == &KeyError::InvalidKey => {
+                    debug!(%source, "Recovered key is incorrect, trying other shares");
+                }
+                Err(report) => return Err(report),
+            }
         }
-        components.push(key);
     }
-    if components.is_empty() {
-        return Err(Report::new(KeyError::Required));
+    Err(Report::new(KeyError::Shares)
+        .attach(format!(
+            "No {threshold} of the {} available shares recover the key",
+            shares.len()
+        ))
+        .attach_path(&path))
+}
+
+/// Get every combination of `size` items, keeping their order.
+fn get_combinations<T>(items: &[T], size: usize) -> Vec<Vec<&T>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let mut combinations = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let rest = items.get(index + 1..).unwrap_or_default();
+        for mut combination in get_combinations(rest, size - 1) {
+            combination.insert(0, item);
+            combinations.push(combination);
+        }
     }
-    Ok(components.join(""))
+    combinations
 }
 
-#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[derive(Clone, Debug, Error, PartialEq)]
 pub enum KeyError {
     #[error("Unable to read key file")]
     KeyFile,
+    #[error("Key file is not private to root, {} has owner {uid} and mode {mode:o}", path.display())]
+    InsecureKeyFile { path: PathBuf, uid: u32, mode: u32 },
+    #[error("Unable to confirm the key file is on removable media")]
+    Removable,
     #[error("Unable to read key from TPM")]
     Tpm,
     #[error("Unable to read key from prompt")]
     Prompt,
+    #[error("Unable to read key from keyring")]
+    Keyring,
+    #[error("Unable to read systemd credential")]
+    Credential,
+    #[error("Unable to recover key from Tang server")]
+    Tang,
+    #[error("`$CREDENTIALS_DIRECTORY` is not set, run as a systemd service with `LoadCredential=`")]
+    CredentialsDirectory,
+    #[error("Unable to recover key from shares")]
+    Shares,
+    #[error("Unable to read key sources from LUKS2 token")]
+    Token,
     #[error("At least one key source must be provided")]
     Required,
     #[error("Key is incorrect")]
     InvalidKey,
     #[error("Failed to unlock LUKS partition")]
     Unlock,
+    #[error("LUKS partition is busy or already unlocked")]
+    Busy,
+    #[error("Unable to find the keyslot of the key")]
+    KeySlot,
     #[error("Key already exists")]
     Exists,
     #[error("Failed to add LUKS key")]
     Add,
 }
 
+impl KeyError {
+    /// Get the error for a failed `cryptsetup` exit status.
+    ///
+    /// - <https://man7.org/linux/man-pages/man8/cryptsetup.8.html#RETURN_CODES>
+    #[must_use]
+    pub fn from_exit_status(status: ExitStatus, default: Self) -> Self {
+        match status.code() {
+            Some(2) => Self::InvalidKey,
+            Some(5) => Self::Busy,
+            _ => default,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::os::unix::process::ExitStatusExt;
 
     #[test]
     fn _get_key() {
         // Arrange
-        let options = Options::read_options(None).expect("Should be able to read options");
+        let options = Options::read_options(None, None).expect("Should be able to read options");
 
         // Act
         let result = get_key(&options);
@@ -82,4 +190,38 @@ mod tests {
         // Assert
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn _get_combinations() {
+        // Arrange
+        let items = [1, 2, 3];
+
+        // Act
+        let pairs = get_combinations(&items, 2);
+        let none = get_combinations(&items, 0);
+        let too_many = get_combinations(&items, 4);
+
+        // Assert
+        assert_eq!(pairs, vec![vec![&1, &2], vec![&1, &3], vec![&2, &3]]);
+        assert_eq!(none, vec![Vec::<&i32>::new()]);
+        assert!(too_many.is_empty());
+    }
+
+    #[test]
+    fn _from_exit_status() {
+        // Arrange
+        let invalid_key = ExitStatus::from_raw(2 << 8);
+        let busy = ExitStatus::from_raw(5 << 8);
+        let other = ExitStatus::from_raw(1 << 8);
+
+        // Act
+        let invalid_key = KeyError::from_exit_status(invalid_key, KeyError::Unlock);
+        let busy = KeyError::from_exit_status(busy, KeyError::Unlock);
+        let other = KeyError::from_exit_status(other, KeyError::Unlock);
+
+        // Assert
+        assert_eq!(invalid_key, KeyError::InvalidKey);
+        assert_eq!(busy, KeyError::Busy);
+        assert_eq!(other, KeyError::Unlock);
+    }
 }
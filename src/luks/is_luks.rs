@@ -33,7 +33,7 @@ mod tests {
         assert!(is_root().is_ok(), "Root is required to run this test");
 
         // Arrange
-        let options = Options::read_options(None).expect("Should be able to read options");
+        let options = Options::read_options(None, None).expect("Should be able to read options");
 
         // Act
         let result = is_luks_partition(&options);
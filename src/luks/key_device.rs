@@ -0,0 +1,289 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::remove_dir;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Default number of seconds to wait for the key device to appear.
+pub const DEFAULT_KEY_DEVICE_TIMEOUT: u64 = 30;
+
+/// Interval between checks for the key device.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Removable device that holds the key file.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "RawKeyDevice", into = "RawKeyDevice")]
+pub enum KeyDevice {
+    /// Filesystem UUID
+    ///
+    /// Example: `1234-ABCD`
+    Uuid(String),
+    /// Filesystem label
+    ///
+    /// Example: `KEYS`
+    Label(String),
+}
+
+/// Value of the `key_device` option as written in an options file.
+///
+/// A map with either `uuid` or `label` so the device is written as `uuid: 1234-ABCD` rather
+/// than with a YAML tag.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RawKeyDevice {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+impl TryFrom<RawKeyDevice> for KeyDevice {
+    type Error = KeyDeviceError;
+
+    fn try_from(raw: RawKeyDevice) -> Result<Self, Self::Error> {
+        match (raw.uuid, raw.label) {
+            (Some(uuid), None) => Ok(KeyDevice::Uuid(uuid)),
+            (None, Some(label)) => Ok(KeyDevice::Label(label)),
+            _ => Err(KeyDeviceError::Identifier),
+        }
+    }
+}
+
+impl From<KeyDevice> for RawKeyDevice {
+    fn from(device: KeyDevice) -> Self {
+        match device {
+            KeyDevice::Uuid(uuid) => RawKeyDevice {
+                uuid: Some(uuid),
+                label: None,
+            },
+            KeyDevice::Label(label) => RawKeyDevice {
+                uuid: None,
+                label: Some(label),
+            },
+        }
+    }
+}
+
+impl KeyDevice {
+    /// Get the path of the udev symlink to the device.
+    ///
+    /// - <https://wiki.archlinux.org/title/Persistent_block_device_naming>
+    #[must_use]
+    pub fn get_path(&self) -> PathBuf {
+        match self {
+            KeyDevice::Uuid(uuid) => PathBuf::from("/dev/disk/by-uuid").join(uuid),
+            KeyDevice::Label(label) => {
+                PathBuf::from("/dev/disk/by-label").join(escape_label(label))
+            }
+        }
+    }
+}
+
+impl Display for KeyDevice {
+    #[allow(clippy::absolute_paths)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyDevice::Uuid(uuid) => write!(f, "UUID={uuid}"),
+            KeyDevice::Label(label) => write!(f, "LABEL={label}"),
+        }
+    }
+}
+
+/// Escape a label the same way udev does for its `by-label` symlinks.
+fn escape_label(label: &str) -> String {
+    label
+        .chars()
+        .map(|character| match character {
+            ' ' => "\\x20".to_owned(),
+            '/' => "\\x2f".to_owned(),
+            _ => character.to_string(),
+        })
+        .collect()
+}
+
+/// Read the key file from a removable device.
+///
+/// Wait for the device to appear, mount it read-only at a private temporary mount point,
+/// read the file at `key_path` relative to the root of the device, then unmount it again.
+pub fn read_key_from_device(
+    options: &Options,
+    device: &KeyDevice,
+    key_path: &Path,
+) -> Result<Secret, Report<KeyDeviceError>> {
+    let device_path = wait_for_key_device(options, device)?;
+    let mount_point = create_private_dir()
+        .change_context(KeyDeviceError::MountPoint)
+        .attach_key_value("Device", &device.to_string())?;
+    let result = mount_key_device(&device_path, &mount_point).and_then(|()| {
+        let path = mount_point.join(key_path.strip_prefix("/").unwrap_or(key_path));
+        let key = read_key_file(&path, options.key_offset, options.key_size)
+            .change_context(KeyDeviceError::Read)
+            .attach_path(key_path);
+        let unmounted = unmount_key_device(&mount_point);
+        let key = key?;
+        unmounted?;
+        Ok(key)
+    });
+    if let Err(error) = remove_dir(&mount_point) {
+        warn!(path = %mount_point.display(), "Unable to remove key device mount point: {error}");
+    }
+    result.attach_key_value("Device", &device.to_string())
+}
+
+/// Wait for the key device to appear for up to `key_device_timeout` seconds.
+///
+/// Returns the path of the udev symlink to the device.
+pub fn wait_for_key_device(
+    options: &Options,
+    device: &KeyDevice,
+) -> Result<PathBuf, Report<KeyDeviceError>> {
+    let timeout = options
+        .key_device_timeout
+        .unwrap_or(DEFAULT_KEY_DEVICE_TIMEOUT);
+    let device_path = device.get_path();
+    wait_for_device(&device_path, Duration::from_secs(timeout))
+        .attach_key_value("Device", &device.to_string())?;
+    Ok(device_path)
+}
+
+/// Poll until the device path exists.
+fn wait_for_device(path: &Path, timeout: Duration) -> Result<(), Report<KeyDeviceError>> {
+    let start = Instant::now();
+    if !path.exists() {
+        info!("Waiting up to {}s for key device", timeout.as_secs());
+    }
+    while !path.exists() {
+        if start.elapsed() >= timeout {
+            return Err(Report::new(KeyDeviceError::Timeout).attach_path(path));
+        }
+        sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// - <https://man7.org/linux/man-pages/man8/mount.8.html>
+fn mount_key_device(device: &Path, mount_point: &Path) -> Result<(), Report<KeyDeviceError>> {
+    Command::new("mount")
+        .arg("--read-only")
+        .arg("--options")
+        .arg("nodev,nosuid,noexec")
+        .arg(device.display().to_string())
+        .arg(mount_point.display().to_string())
+        .output()
+        .expect("should be able to execute `mount`")
+        .ok_or(KeyDeviceError::Mount)
+        .attach_path(device)
+}
+
+/// - <https://man7.org/linux/man-pages/man8/umount.8.html>
+fn unmount_key_device(mount_point: &Path) -> Result<(), Report<KeyDeviceError>> {
+    Command::new("umount")
+        .arg(mount_point.display().to_string())
+        .output()
+        .expect("should be able to execute `umount`")
+        .ok_or(KeyDeviceError::Unmount)
+        .attach_path(mount_point)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum KeyDeviceError {
+    #[error("Timed out waiting for key device")]
+    Timeout,
+    #[error("Unable to create key device mount point")]
+    MountPoint,
+    #[error("Unable to mount key device")]
+    Mount,
+    #[error("Unable to read key file from key device")]
+    Read,
+    #[error("Unable to unmount key device")]
+    Unmount,
+    #[error("Key device must have either a `uuid` or a `label`")]
+    Identifier,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_device_get_path() {
+        // Arrange
+        let uuid = KeyDevice::Uuid("1234-ABCD".to_owned());
+        let label = KeyDevice::Label("MY KEYS".to_owned());
+
+        // Act
+        let uuid = uuid.get_path();
+        let label = label.get_path();
+
+        // Assert
+        assert_eq!(uuid, PathBuf::from("/dev/disk/by-uuid/1234-ABCD"));
+        assert_eq!(label, PathBuf::from("/dev/disk/by-label/MY\\x20KEYS"));
+    }
+
+    #[test]
+    fn deserialize_key_device() {
+        // Arrange
+        // Act
+        let device: KeyDevice =
+            serde_yaml::from_str("uuid: 1234-ABCD").expect("should deserialize uuid");
+
+        // Assert
+        assert_eq!(device, KeyDevice::Uuid("1234-ABCD".to_owned()));
+    }
+
+    #[test]
+    fn deserialize_key_device_options() {
+        // Arrange
+        let yaml = "key_device:\n  label: KEYS\n";
+
+        // Act
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).expect("should parse yaml");
+        let device: KeyDevice = serde_yaml::from_value(
+            value
+                .get("key_device")
+                .expect("should have key_device")
+                .clone(),
+        )
+        .expect("should deserialize label");
+
+        // Assert
+        assert_eq!(device, KeyDevice::Label("KEYS".to_owned()));
+    }
+
+    #[test]
+    fn deserialize_key_device_invalid() {
+        // Arrange
+        // Act
+        let both = serde_yaml::from_str::<KeyDevice>("uuid: 1234-ABCD\nlabel: KEYS");
+        let neither = serde_yaml::from_str::<KeyDevice>("{}");
+
+        // Assert
+        assert!(both.is_err());
+        assert!(neither.is_err());
+    }
+
+    #[test]
+    fn serialize_key_device() {
+        // Arrange
+        let device = KeyDevice::Uuid("1234-ABCD".to_owned());
+
+        // Act
+        let json = serde_json::to_string(&device).expect("should serialize");
+
+        // Assert
+        assert_eq!(json, r#"{"uuid":"1234-ABCD"}"#);
+    }
+
+    #[test]
+    fn wait_for_device_timeout() {
+        // Arrange
+        let path = PathBuf::from("/dev/disk/by-uuid/does-not-exist");
+
+        // Act
+        let result = wait_for_device(&path, Duration::from_millis(10));
+
+        // Assert
+        let report = result.expect_err("Should time out");
+        assert_eq!(report.current_context(), &KeyDeviceError::Timeout);
+    }
+}
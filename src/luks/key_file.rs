@@ -0,0 +1,173 @@
+use crate::prelude::*;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::os::unix::fs::FileTypeExt;
+use strum::Display;
+
+/// How the contents of the key file are interpreted.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Display, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum KeyEncoding {
+    /// Text with leading and trailing whitespace removed.
+    #[default]
+    Text,
+    /// Bytes used as is.
+    Raw,
+    /// Hex encoded bytes.
+    Hex,
+    /// Base64 encoded bytes with padding.
+    Base64,
+}
+
+impl KeyEncoding {
+    /// Decode the contents of the key file.
+    pub fn decode(self, key: Secret) -> Result<Secret, Report<KeyEncodingError>> {
+        match self {
+            KeyEncoding::Text => Ok(key.trim()),
+            KeyEncoding::Raw => Ok(key),
+            KeyEncoding::Hex => {
+                let key = key.trim();
+                let mut output = Secret::zeroed(key.len().div_ceil(2));
+                hex::decode_to_slice(key.expose(), output.expose_mut())
+                    .change_context(KeyEncodingError::Hex)?;
+                Ok(output)
+            }
+            KeyEncoding::Base64 => {
+                let key = key.trim();
+                let mut output = Secret::zeroed(base64::decoded_len_estimate(key.len()));
+                let length = STANDARD
+                    .decode_slice(key.expose(), output.expose_mut())
+                    .change_context(KeyEncodingError::Base64)?;
+                output.truncate(length);
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// Read `size` bytes of the key file from `offset`.
+///
+/// If `size` isn't set then the rest of the file is read. Similar to the `--keyfile-offset`
+/// and `--keyfile-size` arguments of `cryptsetup` so the key can be part of a larger file
+/// or a raw device. `size` is required for a block device as its length is unknown.
+#[allow(clippy::absolute_paths)]
+pub fn read_key_file(
+    path: &Path,
+    offset: Option<u64>,
+    size: Option<usize>,
+) -> Result<Secret, std::io::Error> {
+    read_key_from_file(File::open(path)?, offset, size)
+}
+
+/// Read `size` bytes from `offset` of a key file that has already been opened.
+///
+/// See [`read_key_file`].
+#[allow(clippy::absolute_paths)]
+pub fn read_key_from_file(
+    mut file: File,
+    offset: Option<u64>,
+    size: Option<usize>,
+) -> Result<Secret, std::io::Error> {
+    let offset = offset.unwrap_or_default();
+    file.seek(SeekFrom::Start(offset))?;
+    if let Some(size) = size {
+        let mut key = Secret::zeroed(size);
+        file.read_exact(key.expose_mut())?;
+        return Ok(key);
+    }
+    let metadata = file.metadata()?;
+    if metadata.file_type().is_block_device() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "`key_size` is required to read a key from a block device",
+        ));
+    }
+    let length = metadata.len().saturating_sub(offset);
+    let length = usize::try_from(length).expect("Key file length should fit in usize");
+    Secret::read_from(file, length)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum KeyEncodingError {
+    #[error("Key file is not valid hex")]
+    Hex,
+    #[error("Key file is not valid base64")]
+    Base64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[test]
+    fn read_key_file_offset_size() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("test.key");
+        write(&path, [0_u8, 1, 2, 3, 4, 5]).expect("Should be able to write key file");
+
+        // Act
+        let key = read_key_file(&path, Some(2), Some(3)).expect("Should be able to read key");
+
+        // Assert
+        assert_eq!(key.expose(), [2, 3, 4]);
+    }
+
+    #[test]
+    fn read_key_file_offset() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("test.key");
+        write(&path, [0_u8, 1, 2, 3, 4, 5]).expect("Should be able to write key file");
+
+        // Act
+        let key = read_key_file(&path, Some(4), None).expect("Should be able to read key");
+
+        // Assert
+        assert_eq!(key.expose(), [4, 5]);
+    }
+
+    #[test]
+    fn key_encoding_decode() {
+        // Arrange
+        let input = "  AAEC/w==\n";
+
+        // Act
+        let text = KeyEncoding::Text.decode(Secret::from(input.to_owned()));
+        let raw = KeyEncoding::Raw.decode(Secret::from(input.to_owned()));
+        let hex = KeyEncoding::Hex.decode(Secret::from("000102ff\n".to_owned()));
+        let base64 = KeyEncoding::Base64.decode(Secret::from(input.to_owned()));
+
+        // Assert
+        assert_eq!(text.expect("Should decode text").expose(), b"AAEC/w==");
+        assert_eq!(raw.expect("Should decode raw").expose(), input.as_bytes());
+        assert_eq!(hex.expect("Should decode hex").expose(), [0, 1, 2, 255]);
+        assert_eq!(
+            base64.expect("Should decode base64").expose(),
+            [0, 1, 2, 255]
+        );
+    }
+
+    #[test]
+    fn key_encoding_decode_invalid() {
+        // Arrange
+        // Act
+        let hex = KeyEncoding::Hex.decode(Secret::from("0g".to_owned()));
+        let odd_hex = KeyEncoding::Hex.decode(Secret::from("000".to_owned()));
+        let base64 = KeyEncoding::Base64.decode(Secret::from("!".to_owned()));
+
+        // Assert
+        assert!(hex.is_err());
+        assert!(odd_hex.is_err());
+        assert!(base64.is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::prelude::*;
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sharks::{Share, Sharks};
+use std::collections::BTreeMap;
+use std::fs::{File, read_to_string, remove_file, rename};
+use std::io::Read;
+
+/// Version of the shares file format.
+const SHARES_VERSION: u8 = 1;
+
+/// Length in bytes of the random volume secret that is split into shares.
+const VOLUME_SECRET_LENGTH: usize = 32;
+
+/// Salt of the HKDF-SHA256 derivation of the mask that wraps each share.
+const MASK_SALT: &[u8] = b"mount-luks";
+
+/// Prefix of the context of the mask derivation, followed by the key source.
+const MASK_INFO_PREFIX: &str = "mount-luks/share/v1/";
+
+/// Shares of the volume secret wrapped by the key component of each source.
+///
+/// A share on its own reveals nothing about the volume secret, and it's wrapped so it can
+/// only be recovered with the key component of its source. Nothing in the file tells whether
+/// a share was unwrapped with the right component, so a guessed component can only be
+/// checked by trying the recovered key against LUKS.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct KeyShares {
+    /// Version of the file format
+    pub version: u8,
+    /// Number of shares required to recover the volume secret
+    pub threshold: u8,
+    /// Wrapped share of each key source
+    pub shares: BTreeMap<KeySource, WrappedShare>,
+}
+
+/// Share masked with XOR by a value derived from the key component of its source.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WrappedShare {
+    /// Wrapped share encoded as hex
+    pub share: String,
+}
+
+impl KeyShares {
+    /// Split a new random volume secret into one share per key component.
+    ///
+    /// Any `threshold` of the components can recover the LUKS passphrase, which is returned
+    /// along with the wrapped shares.
+    pub fn split(
+        threshold: u8,
+        components: &[(KeySource, Secret)],
+    ) -> Result<(Secret, KeyShares), Report<KeySharesError>> {
+        if threshold == 0 || usize::from(threshold) > components.len() {
+            return Err(Report::new(KeySharesError::Threshold)
+                .attach_key_value("Threshold", &threshold.to_string())
+                .attach_key_value("Sources", &components.len().to_string()));
+        }
+        let mut secret = Secret::zeroed(VOLUME_SECRET_LENGTH);
+        File::open("/dev/urandom")
+            .and_then(|mut file| file.read_exact(secret.expose_mut()))
+            .change_context(KeySharesError::Random)?;
+        let dealer = Sharks(threshold).dealer(secret.expose());
+        let mut shares = BTreeMap::new();
+        for ((source, component), share) in components.iter().zip(dealer) {
+            let share = Secret::new(Vec::from(&share));
+            shares.insert(*source, wrap_share(*source, component, &share));
+        }
+        let key_shares = KeyShares {
+            version: SHARES_VERSION,
+            threshold,
+            shares,
+        };
+        Ok((encode_passphrase(&secret), key_shares))
+    }
+
+    /// Unwrap the share of `source` with its key component.
+    ///
+    /// The wrong component gives a share that recovers the wrong key.
+    pub fn unwrap_share(
+        &self,
+        source: KeySource,
+        component: &Secret,
+    ) -> Result<Share, Report<KeySharesError>> {
+        let wrapped = self
+            .shares
+            .get(&source)
+            .ok_or_else(|| Report::new(KeySharesError::Missing))
+            .attach_key_value("Source", &source.to_string())?;
+        let mut share = hex::decode(&wrapped.share)
+            .map(Secret::new)
+            .change_context(KeySharesError::Deserialize)
+            .attach_key_value("Source", &source.to_string())?;
+        let mask = derive_mask(source, component, share.len());
+        for (byte, mask) in share.expose_mut().iter_mut().zip(mask.expose()) {
+            *byte ^= mask;
+        }
+        Share::try_from(share.expose())
+            .map_err(|error| Report::new(KeySharesError::Deserialize).attach(error))
+            .attach_key_value("Source", &source.to_string())
+    }
+
+    /// Recover the LUKS passphrase from at least `threshold` unwrapped shares.
+    pub fn recover(&self, shares: &[&Share]) -> Result<Secret, Report<KeySharesError>> {
+        let secret = Sharks(self.threshold)
+            .recover(shares.iter().copied())
+            .map(Secret::new)
+            .map_err(|error| Report::new(KeySharesError::Recover).attach(error.to_owned()))
+            .attach_key_value("Threshold", &self.threshold.to_string())
+            .attach_key_value("Shares", &shares.len().to_string())?;
+        Ok(encode_passphrase(&secret))
+    }
+}
+
+/// Read the shares file of a volume.
+pub fn read_key_shares(path: &Path) -> Result<KeyShares, Report<KeySharesError>> {
+    let content = read_to_string(path)
+        .change_context(KeySharesError::Read)
+        .attach_path(path)?;
+    let shares: KeyShares = serde_yaml::from_str(&content)
+        .change_context(KeySharesError::Deserialize)
+        .attach_path(path)?;
+    if shares.version != SHARES_VERSION {
+        return Err(Report::new(KeySharesError::Version)
+            .attach_key_value("Version", &shares.version.to_string())
+            .attach_path(path));
+    }
+    Ok(shares)
+}
+
+/// Write the shares file of a volume.
+pub fn write_key_shares(path: &Path, shares: &KeyShares) -> Result<(), Report<KeySharesError>> {
+    let content = serde_yaml::to_string(shares).change_context(KeySharesError::Write)?;
+    write_private_file(path, content.as_bytes())
+        .change_context(KeySharesError::Write)
+        .attach_path(path)
+}
+
+/// Write the shares file of a volume to a staged path next to `path`.
+///
+/// The staged file is only moved into place by [`commit_key_shares`] once the key has been
+/// added and checked, so a failure in between leaves the previous shares file usable.
+pub fn stage_key_shares(
+    path: &Path,
+    shares: &KeyShares,
+) -> Result<PathBuf, Report<KeySharesError>> {
+    let mut staged = path.as_os_str().to_owned();
+    staged.push(".new");
+    let staged = PathBuf::from(staged);
+    write_key_shares(&staged, shares)?;
+    Ok(staged)
+}
+
+/// Replace the shares file at `path` with a file written by [`stage_key_shares`].
+pub fn commit_key_shares(staged: &Path, path: &Path) -> Result<(), Report<KeySharesError>> {
+    rename(staged, path)
+        .change_context(KeySharesError::Write)
+        .attach_path(path)
+}
+
+/// Remove a file written by [`stage_key_shares`] that won't be used.
+pub fn discard_key_shares(staged: &Path) {
+    if let Err(error) = remove_file(staged) {
+        warn!(path = %staged.display(), "Unable to remove staged shares file: {error}");
+    }
+}
+
+fn wrap_share(source: KeySource, component: &Secret, share: &Secret) -> WrappedShare {
+    let mask = derive_mask(source, component, share.len());
+    let wrapped: Vec<u8> = share
+        .expose()
+        .iter()
+        .zip(mask.expose())
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    WrappedShare {
+        share: hex::encode(wrapped),
+    }
+}
+
+/// Derive the mask of a share from the key component of its source.
+///
+/// The source is part of the context so the same component used by two sources gives
+/// different masks.
+fn derive_mask(source: KeySource, component: &Secret, length: usize) -> Secret {
+    let hkdf = Hkdf::<Sha256>::new(Some(MASK_SALT), component.expose());
+    let info = format!("{MASK_INFO_PREFIX}{source}");
+    let mut mask = Secret::zeroed(length);
+    hkdf.expand(info.as_bytes(), mask.expose_mut())
+        .expect("Share length should be valid for HKDF-SHA256");
+    mask
+}
+
+/// Encode the volume secret as lowercase hex to use as the LUKS passphrase.
+fn encode_passphrase(secret: &Secret) -> Secret {
+    let mut passphrase = Secret::zeroed(secret.len() * 2);
+    hex::encode_to_slice(secret.expose(), passphrase.expose_mut())
+        .expect("Hex buffer should be twice the secret length");
+    passphrase
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum KeySharesError {
+    #[error("Threshold must be between 1 and the number of key sources")]
+    Threshold,
+    #[error("Unable to generate volume secret")]
+    Random,
+    #[error("Unable to read shares file")]
+    Read,
+    #[error("Unable to deserialize shares file")]
+    Deserialize,
+    #[error("Unsupported shares file version")]
+    Version,
+    #[error("Unable to write shares file")]
+    Write,
+    #[error("Key source does not have a share, run `set-luks` again")]
+    Missing,
+    #[error("Unable to recover volume secret from shares")]
+    Recover,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_components(values: &[(KeySource, &str)]) -> Vec<(KeySource, Secret)> {
+        values
+            .iter()
+            .map(|&(source, value)| (source, Secret::from(value.to_owned())))
+            .collect()
+    }
+
+    #[test]
+    fn key_shares_recover_threshold() {
+        // Arrange
+        let components = to_components(&[
+            (KeySource::File, "file"),
+            (KeySource::Tpm, "tpm"),
+            (KeySource::Prompt, "1234"),
+        ]);
+        let (passphrase, shares) =
+            KeyShares::split(2, &components).expect("Should be able to split secret");
+
+        // Act
+        let unwrapped: Vec<Share> = components
+            .iter()
+            .skip(1)
+            .map(|(source, component)| {
+                shares
+                    .unwrap_share(*source, component)
+                    .expect("Should be able to unwrap share")
+            })
+            .collect();
+        let recovered = shares
+            .recover(&unwrapped.iter().collect::<Vec<_>>())
+            .expect("Should be able to recover secret");
+
+        // Assert
+        assert_eq!(recovered.expose(), passphrase.expose());
+        assert_eq!(passphrase.len(), VOLUME_SECRET_LENGTH * 2);
+    }
+
+    #[test]
+    fn key_shares_below_threshold() {
+        // Arrange
+        let components = to_components(&[(KeySource::File, "file"), (KeySource::Tpm, "tpm")]);
+        let (_, shares) = KeyShares::split(2, &components).expect("Should be able to split secret");
+        let (source, component) = components.first().expect("Should have a component");
+        let share = shares
+            .unwrap_share(*source, component)
+            .expect("Should be able to unwrap share");
+
+        // Act
+        let result = shares.recover(&[&share]);
+
+        // Assert
+        let report = result.expect_err("Should require two shares");
+        assert_eq!(report.current_context(), &KeySharesError::Recover);
+    }
+
+    #[test]
+    fn key_shares_wrong_component() {
+        // Arrange
+        let components = to_components(&[(KeySource::File, "file"), (KeySource::Tpm, "tpm")]);
+        let (passphrase, shares) =
+            KeyShares::split(1, &components).expect("Should be able to split secret");
+
+        // Act
+        let share = shares
+            .unwrap_share(KeySource::File, &Secret::from("tpm".to_owned()))
+            .expect("Should unwrap without checking the component");
+        let result = shares.recover(&[&share]);
+
+        // Assert
+        if let Ok(recovered) = result {
+            assert_ne!(recovered.expose(), passphrase.expose());
+        }
+    }
+
+    #[test]
+    fn key_shares_round_trip() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("e.shares");
+        let components = to_components(&[(KeySource::File, "file"), (KeySource::Tpm, "tpm")]);
+        let (_, shares) = KeyShares::split(2, &components).expect("Should be able to split secret");
+
+        // Act
+        write_key_shares(&path, &shares).expect("Should be able to write shares");
+        let result = read_key_shares(&path);
+
+        // Assert
+        assert_eq!(result.expect("Should be able to read shares"), shares);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::prelude::*;
+
+/// Get the active keyslots of the LUKS partition.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-luksDump.8.html>
+pub fn get_key_slots(options: &Options) -> Result<Vec<u32>, Report<KeySlotError>> {
+    let output = Command::new("cryptsetup")
+        .arg("luksDump")
+        .arg(options.partition_path.display().to_string())
+        .output()
+        .expect("should be able to execute `cryptsetup luksDump`");
+    if !output.status.success() {
+        return Err(Report::new(KeySlotError::Dump)
+            .attach_response(output.to_response())
+            .attach_path(&options.partition_path));
+    }
+    Ok(parse_key_slots(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse the active keyslots from the output of `cryptsetup luksDump`.
+///
+/// LUKS1 lists every keyslot as `Key Slot 0: ENABLED` or `Key Slot 1: DISABLED`. LUKS2 only
+/// lists the active keyslots, indented under `Keyslots:`.
+fn parse_key_slots(output: &str) -> Vec<u32> {
+    let mut slots = Vec::new();
+    let mut is_luks2_section = false;
+    for line in output.lines() {
+        if let Some(status) = line.strip_prefix("Key Slot ") {
+            if let Some((slot, "ENABLED")) = status.split_once(": ")
+                && let Ok(slot) = slot.parse()
+            {
+                slots.push(slot);
+            }
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            is_luks2_section = line.trim_end() == "Keyslots:";
+            continue;
+        }
+        if !is_luks2_section || line.starts_with('\t') {
+            continue;
+        }
+        if let Some((slot, _)) = line.trim().split_once(':')
+            && let Ok(slot) = slot.parse()
+        {
+            slots.push(slot);
+        }
+    }
+    slots
+}
+
+/// Wipe a keyslot of the LUKS partition.
+///
+/// Runs in batch mode so a passphrase of another keyslot isn't required. The caller must
+/// check the keyslot isn't the last one.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-luksKillSlot.8.html>
+pub fn remove_key_slot(options: &Options, slot: u32) -> Result<(), Report<KeySlotError>> {
+    Command::new("cryptsetup")
+        .arg("luksKillSlot")
+        .arg("--batch-mode")
+        .arg(options.partition_path.display().to_string())
+        .arg(slot.to_string())
+        .output()
+        .expect("should be able to execute `cryptsetup luksKillSlot`")
+        .ok_or(KeySlotError::Remove)
+        .attach_key_value("Keyslot", &slot.to_string())
+}
+
+/// Get the keyslot that belongs to mount-luks.
+///
+/// The `key_slot` option takes precedence over the LUKS2 token, which is only used if it
+/// belongs to exactly one keyslot.
+pub fn get_owned_key_slot(
+    options: &Options,
+    token: Option<&LuksToken>,
+) -> Result<u32, Report<KeySlotError>> {
+    if let Some(slot) = options.key_slot {
+        return Ok(slot);
+    }
+    match token.map(LuksToken::get_keyslots).as_deref() {
+        Some([slot]) => Ok(*slot),
+        _ => Err(Report::new(KeySlotError::Unknown)),
+    }
+}
+
+/// Check the keyslot is active and isn't the last active keyslot.
+pub fn check_removable_key_slot(active: &[u32], slot: u32) -> Result<(), Report<KeySlotError>> {
+    if !active.contains(&slot) {
+        return Err(
+            Report::new(KeySlotError::Inactive).attach_key_value("Keyslot", &slot.to_string())
+        );
+    }
+    if active.len() < 2 {
+        return Err(Report::new(KeySlotError::Last).attach_key_value("Keyslot", &slot.to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum KeySlotError {
+    #[error("Unable to read LUKS keyslots")]
+    Dump,
+    #[error("Unable to tell which keyslot belongs to mount-luks, set `key_slot` in the options")]
+    Unknown,
+    #[error("Keyslot is not active")]
+    Inactive,
+    #[error("Refusing to remove the last active keyslot")]
+    Last,
+    #[error("Failed to remove LUKS keyslot")]
+    Remove,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_slots_luks1() {
+        // Arrange
+        let output = "LUKS header information for /dev/sda1\n\
+            \n\
+            Version:       \t1\n\
+            Key Slot 0: ENABLED\n\
+            \tIterations:         \t2000000\n\
+            Key Slot 1: DISABLED\n\
+            Key Slot 2: ENABLED\n";
+
+        // Act
+        let slots = parse_key_slots(output);
+
+        // Assert
+        assert_eq!(slots, vec![0, 2]);
+    }
+
+    #[test]
+    fn parse_key_slots_luks2() {
+        // Arrange
+        let output = "LUKS header information\n\
+            Version:       \t2\n\
+            \n\
+            Keyslots:\n  \
+            0: luks2\n\
+            \tKey:        512 bits\n\
+            \tPriority:   normal\n  \
+            3: luks2\n\
+            \tKey:        512 bits\n\
+            Tokens:\n  \
+            0: mount-luks\n\
+            \tKeyslot:    3\n\
+            Digests:\n  \
+            0: pbkdf2\n";
+
+        // Act
+        let slots = parse_key_slots(output);
+
+        // Assert
+        assert_eq!(slots, vec![0, 3]);
+    }
+
+    #[test]
+    fn _check_removable_key_slot() {
+        // Arrange
+        let slot = 1;
+
+        // Act
+        let removable = check_removable_key_slot(&[0, 1], slot);
+        let last = check_removable_key_slot(&[1], slot);
+        let inactive = check_removable_key_slot(&[0, 2], slot);
+
+        // Assert
+        assert!(removable.is_ok());
+        assert_eq!(
+            last.expect_err("Should refuse the last keyslot")
+                .current_context(),
+            &KeySlotError::Last
+        );
+        assert_eq!(
+            inactive
+                .expect_err("Should refuse an inactive keyslot")
+                .current_context(),
+            &KeySlotError::Inactive
+        );
+    }
+}
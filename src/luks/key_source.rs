@@ -0,0 +1,260 @@
+use crate::prelude::*;
+use rpassword::prompt_password;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use strum::Display;
+
+/// Source of a key component.
+///
+/// The order of the variants is the order the sources are read in.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Display, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum KeySource {
+    /// Key file, optionally on a removable key device
+    File,
+    /// TPM sealed object, optionally cached in the user keyring
+    Tpm,
+    /// Interactive prompt
+    Prompt,
+    /// User keyring
+    Keyring,
+    /// systemd credential
+    Credential,
+    /// Tang server
+    Tang,
+}
+
+/// Get the key sources configured for a volume in the order they are read.
+#[must_use]
+pub fn get_sources(options: &Options) -> Vec<KeySource> {
+    let mut sources = Vec::new();
+    if options.key_path.is_some() {
+        sources.push(KeySource::File);
+    }
+    if options.tpm_handle.is_some() {
+        sources.push(KeySource::Tpm);
+    }
+    if options.key_prompt == Some(true) {
+        sources.push(KeySource::Prompt);
+    }
+    if options.keyring.is_some() {
+        sources.push(KeySource::Keyring);
+    }
+    if options.credential.is_some() {
+        sources.push(KeySource::Credential);
+    }
+    if options.tang_url.is_some() {
+        sources.push(KeySource::Tang);
+    }
+    sources
+}
+
+/// Read the key component of a single source.
+pub fn get_component(options: &Options, source: KeySource) -> Result<Secret, Report<KeyError>> {
+    match source {
+        KeySource::File => get_file_component(options),
+        KeySource::Tpm => get_tpm_component(options),
+        KeySource::Prompt => get_prompt_component(),
+        KeySource::Keyring => get_keyring_component(options),
+        KeySource::Credential => get_credential_component(options),
+        KeySource::Tang => get_tang_component(options),
+    }
+}
+
+fn get_file_component(options: &Options) -> Result<Secret, Report<KeyError>> {
+    let path = options
+        .key_path
+        .as_ref()
+        .ok_or_else(|| Report::new(KeyError::KeyFile).attach("`key_path` is not set"))?;
+    if let Some(device) = &options.key_device {
+        trace!(%device, path = %path.display(), "Reading key from device");
+        if options.require_removable_key == Some(true) {
+            let device_path =
+                wait_for_key_device(options, device).change_context(KeyError::KeyFile)?;
+            check_device_removable(&device_path).change_context(KeyError::Removable)?;
+        }
+        let key = read_key_from_device(options, device, path).change_context(KeyError::KeyFile)?;
+        let key = decode_key_file(options, key).attach_path(path)?;
+        if key.is_empty() {
+            warn!(%device, path = %path.display(), "Key file is empty");
+        }
+        return Ok(key);
+    }
+    trace!(path = %path.display(), "Reading key from file");
+    let file = if options.allow_insecure_key_file == Some(true) {
+        warn!(path = %path.display(), "Skipped key file permission check");
+        File::open(path)
+            .change_context(KeyError::KeyFile)
+            .attach_path(path)?
+    } else {
+        open_key_file(path)?
+    };
+    if options.require_removable_key == Some(true) {
+        check_removable(&file, path).change_context(KeyError::Removable)?;
+    }
+    let key = read_key_from_file(file, options.key_offset, options.key_size)
+        .change_context(KeyError::KeyFile)
+        .attach_path(path)?;
+    let key = decode_key_file(options, key).attach_path(path)?;
+    if key.is_empty() {
+        warn!(path = %path.display(), "Key file is empty");
+    }
+    Ok(key)
+}
+
+fn get_tpm_component(options: &Options) -> Result<Secret, Report<KeyError>> {
+    let handle = options.get_tpm_handle().ok_or_else(|| {
+        Report::new(KeyError::Tpm).attach("TPM handle has not been assigned, run `set-tpm` first")
+    })?;
+    let key = if let Some(key) = read_tpm_cache(options) {
+        key
+    } else {
+        trace!(%handle, "Reading key from TPM");
+        let policy = options.get_pcr_policy().change_context(KeyError::Tpm)?;
+        let pin = if options.tpm_pin == Some(true) {
+            let pin = prompt_password("Enter TPM PIN: ")
+                .map(Secret::from)
+                .change_context(KeyError::Prompt)?;
+            Some(pin)
+        } else {
+            None
+        };
+        let key = tpm_backend()
+            .unseal(handle, &policy, pin.as_ref())
+            .change_context(KeyError::Tpm)
+            .attach_key_value("Handle", &handle.to_string())?;
+        write_tpm_cache(options, &key);
+        key
+    };
+    if key.is_empty() {
+        warn!(%handle, "TPM key value is empty");
+    }
+    Ok(key)
+}
+
+fn get_prompt_component() -> Result<Secret, Report<KeyError>> {
+    trace!("Reading key from prompt");
+    let key = prompt_password("Enter interactive key component: ")
+        .map(Secret::from)
+        .change_context(KeyError::Prompt)?
+        .trim();
+    if key.is_empty() {
+        warn!("Prompt value is empty");
+    }
+    Ok(key)
+}
+
+fn get_keyring_component(options: &Options) -> Result<Secret, Report<KeyError>> {
+    let description = options
+        .keyring
+        .as_deref()
+        .ok_or_else(|| Report::new(KeyError::Keyring).attach("`keyring` is not set"))?;
+    trace!(description, "Reading key from keyring");
+    let key = read_keyring_key(description)
+        .change_context(KeyError::Keyring)?
+        .ok_or_else(|| {
+            Report::new(KeyError::Keyring)
+                .attach_key_value("Description", description)
+                .attach("Key was not found or has expired")
+        })?;
+    if key.is_empty() {
+        warn!(description, "Keyring value is empty");
+    }
+    Ok(key)
+}
+
+fn get_credential_component(options: &Options) -> Result<Secret, Report<KeyError>> {
+    let name = options
+        .credential
+        .as_deref()
+        .ok_or_else(|| Report::new(KeyError::Credential).attach("`credential` is not set"))?;
+    trace!(name, "Reading key from systemd credential");
+    let key = read_credential(name)?;
+    if key.is_empty() {
+        warn!(name, "Credential value is empty");
+    }
+    Ok(key)
+}
+
+fn get_tang_component(options: &Options) -> Result<Secret, Report<KeyError>> {
+    let url = options
+        .tang_url
+        .as_deref()
+        .ok_or_else(|| Report::new(KeyError::Tang).attach("`tang_url` is not set"))?;
+    trace!(url, "Recovering key from Tang server");
+    let binding =
+        read_tang_binding(&options.get_tang_binding_path()).change_context(KeyError::Tang)?;
+    if binding.url != url {
+        return Err(Report::new(TangError::Url)
+            .attach_key_value("Bound", &binding.url)
+            .change_context(KeyError::Tang));
+    }
+    binding.recover().change_context(KeyError::Tang)
+}
+
+/// Read the TPM component from the user keyring if `tpm_cache_timeout` is set.
+///
+/// Falls back to the TPM if the cache can't be read.
+fn read_tpm_cache(options: &Options) -> Option<Secret> {
+    options.tpm_cache_timeout?;
+    let description = get_tpm_cache_description(options);
+    match read_keyring_key(&description) {
+        Ok(key) => {
+            if key.is_some() {
+                trace!(description, "Read TPM key from keyring cache");
+            }
+            key
+        }
+        Err(report) => {
+            warn!("Unable to read cached TPM key:\n{report:?}");
+            None
+        }
+    }
+}
+
+/// Cache the TPM component in the user keyring if `tpm_cache_timeout` is set.
+fn write_tpm_cache(options: &Options, key: &Secret) {
+    let Some(timeout) = options.tpm_cache_timeout else {
+        return;
+    };
+    let description = get_tpm_cache_description(options);
+    if let Err(report) = add_keyring_key(&description, key, timeout) {
+        warn!("Unable to cache TPM key:\n{report:?}");
+    }
+}
+
+fn decode_key_file(options: &Options, key: Secret) -> Result<Secret, Report<KeyError>> {
+    let encoding = options.key_encoding.unwrap_or_default();
+    encoding
+        .decode(key)
+        .change_context(KeyError::KeyFile)
+        .attach_key_value("Encoding", &encoding.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _get_sources() {
+        // Arrange
+        let options = Options {
+            key_path: Some(PathBuf::from("/root/.config/mount-luks/e.key")),
+            key_prompt: Some(true),
+            credential: Some("mount-luks.key".to_owned()),
+            ..Options::default()
+        };
+
+        // Act
+        let sources = get_sources(&options);
+
+        // Assert
+        assert_eq!(
+            sources,
+            vec![KeySource::File, KeySource::Prompt, KeySource::Credential]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::prelude::*;
+use std::process::{Output, Stdio};
+
+/// Keyring the keys are stored in.
+///
+/// The user keyring is shared by every session of the user so a key cached by a boot-time
+/// unit can be read by later runs.
+///
+/// - <https://man7.org/linux/man-pages/man7/user-keyring.7.html>
+const KEYRING: &str = "@u";
+
+/// Keyring a new key is held in until its timeout is set.
+///
+/// - <https://man7.org/linux/man-pages/man7/session-keyring.7.html>
+const STAGING_KEYRING: &str = "@s";
+
+/// Type of the keys.
+///
+/// - <https://man7.org/linux/man-pages/man7/keyrings.7.html>
+const KEY_TYPE: &str = "user";
+
+/// Exit code of `keyctl search` if the key isn't found.
+const NOT_FOUND_EXIT_CODE: i32 = 1;
+
+/// Get the description of the cached TPM component of a volume.
+#[must_use]
+pub fn get_tpm_cache_description(options: &Options) -> String {
+    format!("{APP_NAME}:{}:tpm", options.name)
+}
+
+/// Read a key from the user keyring by its description.
+///
+/// Returns `None` if the key isn't found or has expired.
+///
+/// - <https://man7.org/linux/man-pages/man1/keyctl.1.html>
+pub fn read_keyring_key(description: &str) -> Result<Option<Secret>, Report<KeyringError>> {
+    let Some(id) = search_keyring_key(description)? else {
+        return Ok(None);
+    };
+    let (key, output) = Command::new("keyctl")
+        .arg("pipe")
+        .arg(&id)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should be able to spawn `keyctl pipe`")
+        .wait_with_secret()
+        .expect("should be able to wait on `keyctl pipe`");
+    if !output.status.success() {
+        let report = Report::new(KeyringError::Read)
+            .attach_key_value("Description", description)
+            .attach_response(output.to_response());
+        return Err(report);
+    }
+    Ok(Some(key))
+}
+
+/// Check if a key is in the user keyring without reading it.
+///
+/// A key that can't be searched for is reported as missing.
+#[must_use]
+pub fn has_keyring_key(description: &str) -> bool {
+    search_keyring_key(description)
+        .inspect_err(|report| debug!("Unable to search the keyring:\n{report:?}"))
+        .is_ok_and(|id| id.is_some())
+}
+
+/// Find the ID of a key in the user keyring by its description.
+///
+/// Returns `None` if the key isn't found or has expired.
+fn search_keyring_key(description: &str) -> Result<Option<String>, Report<KeyringError>> {
+    let output = Command::new("keyctl")
+        .arg("search")
+        .arg(KEYRING)
+        .arg(KEY_TYPE)
+        .arg(description)
+        .output()
+        .expect("should be able to execute `keyctl search`");
+    if output.status.code() == Some(NOT_FOUND_EXIT_CODE) {
+        return Ok(None);
+    }
+    get_key_id(output, KeyringError::Search, description).map(Some)
+}
+
+/// Add a key to the user keyring that expires after `timeout` seconds, replacing any key with
+/// the same description.
+///
+/// The key is added to a new keyring in the session keyring and only linked into the user
+/// keyring once its timeout is set, so a key that never expires isn't left behind if setting
+/// the timeout fails.
+///
+/// - <https://man7.org/linux/man-pages/man1/keyctl.1.html>
+pub fn add_keyring_key(
+    description: &str,
+    key: &Secret,
+    timeout: u64,
+) -> Result<(), Report<KeyringError>> {
+    let output = Command::new("keyctl")
+        .arg("newring")
+        .arg(format!("{description}:staging"))
+        .arg(STAGING_KEYRING)
+        .output()
+        .expect("should be able to execute `keyctl newring`");
+    let staging = get_key_id(output, KeyringError::Add, description)?;
+    let result = add_staged_key(&staging, description, key, timeout);
+    let unlinked = Command::new("keyctl")
+        .arg("unlink")
+        .arg(&staging)
+        .arg(STAGING_KEYRING)
+        .output()
+        .expect("should be able to execute `keyctl unlink`")
+        .ok_or(KeyringError::Unlink);
+    if let Err(report) = unlinked {
+        warn!("Unable to remove staging keyring:\n{report:?}");
+    }
+    result
+}
+
+/// Add a key to the `staging` keyring, set its timeout and link it into the user keyring.
+fn add_staged_key(
+    staging: &str,
+    description: &str,
+    key: &Secret,
+    timeout: u64,
+) -> Result<(), Report<KeyringError>> {
+    let output = Command::new("keyctl")
+        .arg("padd")
+        .arg(KEY_TYPE)
+        .arg(description)
+        .arg(staging)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should be able to spawn `keyctl padd`")
+        .write_to_stdin(key.expose())
+        .wait_with_output()
+        .expect("should be able to wait on `keyctl padd`");
+    let id = get_key_id(output, KeyringError::Add, description)?;
+    Command::new("keyctl")
+        .arg("timeout")
+        .arg(&id)
+        .arg(timeout.to_string())
+        .output()
+        .expect("should be able to execute `keyctl timeout`")
+        .ok_or(KeyringError::Timeout)
+        .attach_key_value("Description", description)?;
+    Command::new("keyctl")
+        .arg("link")
+        .arg(&id)
+        .arg(KEYRING)
+        .output()
+        .expect("should be able to execute `keyctl link`")
+        .ok_or(KeyringError::Link)
+        .attach_key_value("Description", description)
+}
+
+/// Get the key ID printed by `keyctl`.
+fn get_key_id(
+    output: Output,
+    error: KeyringError,
+    description: &str,
+) -> Result<String, Report<KeyringError>> {
+    let response = output.to_response();
+    if !response.status.success() {
+        let report = Report::new(error)
+            .attach_key_value("Description", description)
+            .attach_response(response);
+        return Err(report);
+    }
+    response.output.ok_or_else(|| {
+        Report::new(error)
+            .attach_key_value("Description", description)
+            .attach("Expected key ID on stdout")
+    })
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum KeyringError {
+    #[error("Unable to search the keyring")]
+    Search,
+    #[error("Unable to read key from the keyring")]
+    Read,
+    #[error("Unable to add key to the keyring")]
+    Add,
+    #[error("Unable to set the timeout of the key")]
+    Timeout,
+    #[error("Unable to link the key into the user keyring")]
+    Link,
+    #[error("Unable to remove the staging keyring")]
+    Unlink,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::id;
+
+    #[test]
+    #[ignore = "Adds a key to the user keyring with `keyctl`"]
+    fn keyring_round_trip() {
+        // Arrange
+        let description = format!("{APP_NAME}:test:{}", id());
+        let key = Secret::from("key\nwith newline".to_owned());
+
+        // Act
+        add_keyring_key(&description, &key, 60).expect("Should be able to add key");
+        let result = read_keyring_key(&description).expect("Should be able to read key");
+
+        // Assert
+        let value = result.expect("Should find key");
+        assert_eq!(value.expose(), key.expose());
+    }
+
+    #[test]
+    #[ignore = "Searches the user keyring with `keyctl`"]
+    fn read_keyring_key_not_found() {
+        // Arrange
+        let description = format!("{APP_NAME}:test:missing:{}", id());
+
+        // Act
+        let result = read_keyring_key(&description).expect("Should be able to search");
+
+        // Assert
+        assert!(result.is_none());
+    }
+}
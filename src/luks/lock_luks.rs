@@ -0,0 +1,18 @@
+use crate::prelude::*;
+
+/// Close the mapper device of an unlocked LUKS partition.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-close.8.html>
+pub fn lock_luks(options: &Options) -> Result<(), Report<LockError>> {
+    Command::new("cryptsetup")
+        .arg("luksClose")
+        .arg(&options.mapper_name)
+        .output()
+        .expect("should be able to execute `cryptsetup luksClose`")
+        .ok_or(LockError)
+        .attach_path(&options.get_mapper_path())
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[error("Failed to lock LUKS partition")]
+pub struct LockError;
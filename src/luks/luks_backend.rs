@@ -0,0 +1,96 @@
+use crate::prelude::*;
+
+/// Operations on a LUKS partition.
+///
+/// The implementation is chosen at compile time by the `native-luks` feature.
+pub trait LuksBackend {
+    /// Check if the partition is encrypted with LUKS.
+    fn is_luks(&self, options: &Options) -> Result<(), Report<IsLuksError>>;
+
+    /// Check the key unlocks the partition without activating it.
+    fn check_key(&self, options: &Options, key: &Secret) -> Result<(), Report<KeyError>>;
+
+    /// Find the keyslot that the key unlocks.
+    fn find_key_slot(&self, options: &Options, key: &Secret) -> Result<u32, Report<KeyError>>;
+
+    /// Unlock the partition and create the mapper device.
+    fn unlock(&self, options: &Options, key: &Secret) -> Result<(), Report<KeyError>>;
+
+    /// Add `key` to `key_slot`, or the first free keyslot, authorized by an `existing`
+    /// passphrase.
+    ///
+    /// Returns the keyslot the key was added to.
+    fn add_key(
+        &self,
+        options: &Options,
+        existing: &Secret,
+        key: &Secret,
+        key_slot: Option<u32>,
+    ) -> Result<u32, Report<KeyError>>;
+
+    /// Get the active keyslots.
+    fn get_key_slots(&self, options: &Options) -> Result<Vec<u32>, Report<KeySlotError>>;
+
+    /// Wipe a keyslot without asking for the passphrase of another keyslot.
+    fn remove_key_slot(&self, options: &Options, slot: u32) -> Result<(), Report<KeySlotError>>;
+}
+
+/// Get the LUKS backend chosen by the `native-luks` feature.
+#[must_use]
+pub fn luks_backend() -> impl LuksBackend {
+    #[cfg(feature = "native-luks")]
+    {
+        NativeLuksBackend
+    }
+    #[cfg(not(feature = "native-luks"))]
+    {
+        SubprocessLuksBackend
+    }
+}
+
+/// LUKS backend that executes `cryptsetup`.
+///
+/// Keys are written to stdin so they aren't visible in the process list.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup.8.html>
+pub struct SubprocessLuksBackend;
+
+impl LuksBackend for SubprocessLuksBackend {
+    fn is_luks(&self, options: &Options) -> Result<(), Report<IsLuksError>> {
+        is_luks_partition(options)
+    }
+
+    fn check_key(&self, options: &Options, key: &Secret) -> Result<(), Report<KeyError>> {
+        check_key(options, key)
+    }
+
+    fn find_key_slot(&self, options: &Options, key: &Secret) -> Result<u32, Report<KeyError>> {
+        find_key_slot(options, key)
+    }
+
+    fn unlock(&self, options: &Options, key: &Secret) -> Result<(), Report<KeyError>> {
+        unlock_luks_with_key(options, key)
+    }
+
+    fn add_key(
+        &self,
+        options: &Options,
+        existing: &Secret,
+        key: &Secret,
+        key_slot: Option<u32>,
+    ) -> Result<u32, Report<KeyError>> {
+        add_key_with_passphrase(options, existing, key, key_slot)?;
+        match key_slot {
+            Some(slot) => Ok(slot),
+            None => find_key_slot(options, key),
+        }
+    }
+
+    fn get_key_slots(&self, options: &Options) -> Result<Vec<u32>, Report<KeySlotError>> {
+        get_key_slots(options)
+    }
+
+    fn remove_key_slot(&self, options: &Options, slot: u32) -> Result<(), Report<KeySlotError>> {
+        remove_key_slot(options, slot)
+    }
+}
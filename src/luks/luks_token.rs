@@ -0,0 +1,333 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Stdio;
+
+/// Type of the LUKS2 token written by `set-luks`.
+pub const TOKEN_TYPE: &str = "mount-luks";
+
+/// Version of the token format.
+const TOKEN_VERSION: u8 = 1;
+
+/// Enrolment metadata stored as a token in the LUKS2 header.
+///
+/// The token records how the key was enrolled so a volume can be mounted with an options file
+/// that only names the partition, mapper and mount path. Only the location of key material is
+/// recorded, never the key material itself.
+///
+/// - <https://gitlab.com/cryptsetup/LUKS2-docs>
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LuksToken {
+    /// Token type, always `mount-luks`
+    #[serde(rename = "type")]
+    pub token_type: String,
+    /// Keyslots the token belongs to
+    ///
+    /// LUKS2 stores keyslot numbers as strings.
+    pub keyslots: Vec<String>,
+    /// Version of the token format
+    pub version: u8,
+    /// Key sources in the order they are read
+    pub sources: Vec<KeySource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_encoding: Option<KeyEncoding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_device: Option<KeyDevice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tpm_handle: Option<TpmHandle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tpm_pin: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pcrs: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pcr_bank: Option<PcrBank>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tang_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_derivation: Option<KeyDerivation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_threshold: Option<u8>,
+}
+
+impl LuksToken {
+    /// Record the enrolment of a volume.
+    ///
+    /// The PCR policy is always recorded, with defaults filled in, so a later change of the
+    /// defaults doesn't change the policy a volume was sealed against.
+    #[must_use]
+    pub fn new(options: &Options, keyslots: &[u32]) -> LuksToken {
+        let tpm_handle = options.get_tpm_handle().map(TpmHandle::from);
+        let policy = tpm_handle
+            .and_then(|_| options.get_pcr_policy().ok())
+            .map(|policy| (policy.bank(), policy.pcrs().to_vec()));
+        LuksToken {
+            token_type: TOKEN_TYPE.to_owned(),
+            keyslots: keyslots.iter().map(ToString::to_string).collect(),
+            version: TOKEN_VERSION,
+            sources: get_sources(options),
+            key_path: options.key_path.clone(),
+            key_offset: options.key_offset,
+            key_size: options.key_size,
+            key_encoding: options.key_encoding,
+            key_device: options.key_device.clone(),
+            tpm_handle,
+            tpm_pin: options.tpm_pin,
+            pcrs: policy.as_ref().map(|(_, pcrs)| pcrs.clone()),
+            pcr_bank: policy.map(|(bank, _)| bank),
+            keyring: options.keyring.clone(),
+            credential: options.credential.clone(),
+            tang_url: options.tang_url.clone(),
+            key_derivation: options.key_derivation,
+            key_threshold: options.key_threshold,
+        }
+    }
+
+    /// Fill the options that are not set from the token.
+    ///
+    /// Options that are set take precedence so the options file can still override the header.
+    pub fn apply(&self, options: &mut Options) {
+        fill(&mut options.key_path, self.key_path.as_ref());
+        fill(&mut options.key_offset, self.key_offset.as_ref());
+        fill(&mut options.key_size, self.key_size.as_ref());
+        fill(&mut options.key_encoding, self.key_encoding.as_ref());
+        fill(&mut options.key_device, self.key_device.as_ref());
+        fill(&mut options.tpm_handle, self.tpm_handle.as_ref());
+        fill(&mut options.tpm_pin, self.tpm_pin.as_ref());
+        fill(&mut options.pcrs, self.pcrs.as_ref());
+        fill(&mut options.pcr_bank, self.pcr_bank.as_ref());
+        fill(&mut options.keyring, self.keyring.as_ref());
+        fill(&mut options.credential, self.credential.as_ref());
+        fill(&mut options.tang_url, self.tang_url.as_ref());
+        fill(&mut options.key_derivation, self.key_derivation.as_ref());
+        fill(&mut options.key_threshold, self.key_threshold.as_ref());
+        if options.key_prompt.is_none() && self.sources.contains(&KeySource::Prompt) {
+            options.key_prompt = Some(true);
+        }
+        let sources = get_sources(options);
+        if sources != self.sources {
+            warn!(
+                "Key sources {sources:?} do not match the LUKS2 token {:?}",
+                self.sources
+            );
+        }
+    }
+
+    /// Get the keyslots the token belongs to.
+    #[must_use]
+    pub fn get_keyslots(&self) -> Vec<u32> {
+        self.keyslots
+            .iter()
+            .filter_map(|slot| slot.parse().ok())
+            .collect()
+    }
+}
+
+fn fill<T: Clone>(option: &mut Option<T>, value: Option<&T>) {
+    if option.is_none() {
+        *option = value.cloned();
+    }
+}
+
+/// Read the `mount-luks` token from the LUKS2 header.
+///
+/// Returns the token ID and the token, or `None` if the header doesn't have one.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-luksDump.8.html>
+pub fn read_luks_token(
+    options: &Options,
+) -> Result<Option<(u32, LuksToken)>, Report<LuksTokenError>> {
+    let output = Command::new("cryptsetup")
+        .arg("luksDump")
+        .arg("--dump-json-metadata")
+        .arg(options.partition_path.display().to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .expect("should be able to execute `cryptsetup luksDump`");
+    if !output.status.success() {
+        return Err(Report::new(LuksTokenError::Dump)
+            .attach_response(output.to_response())
+            .attach_path(&options.partition_path));
+    }
+    let metadata: Value =
+        serde_json::from_slice(&output.stdout).change_context(LuksTokenError::Deserialize)?;
+    find_token(&metadata)
+}
+
+/// Find the `mount-luks` token in the LUKS2 JSON metadata.
+fn find_token(metadata: &Value) -> Result<Option<(u32, LuksToken)>, Report<LuksTokenError>> {
+    let Some(tokens) = metadata.get("tokens").and_then(Value::as_object) else {
+        return Ok(None);
+    };
+    for (id, token) in tokens {
+        if token.get("type").and_then(Value::as_str) != Some(TOKEN_TYPE) {
+            continue;
+        }
+        let id: u32 = id
+            .parse()
+            .change_context(LuksTokenError::Deserialize)
+            .attach_key_value("Token", id)?;
+        let token: LuksToken = serde_json::from_value(token.clone())
+            .change_context(LuksTokenError::Deserialize)
+            .attach_key_value("Token", &id.to_string())?;
+        if token.version != TOKEN_VERSION {
+            return Err(Report::new(LuksTokenError::Version)
+                .attach_key_value("Version", &token.version.to_string()));
+        }
+        return Ok(Some((id, token)));
+    }
+    Ok(None)
+}
+
+/// Import a token into the LUKS2 header, replacing the token `id` if set.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-token.8.html>
+pub fn write_luks_token(
+    options: &Options,
+    id: Option<u32>,
+    token: &LuksToken,
+) -> Result<(), Report<LuksTokenError>> {
+    let json = serde_json::to_vec(token).change_context(LuksTokenError::Import)?;
+    let mut command = Command::new("cryptsetup");
+    command.arg("token").arg("import").arg("--json-file=-");
+    if let Some(id) = id {
+        command
+            .arg(format!("--token-id={id}"))
+            .arg("--token-replace");
+    }
+    command
+        .arg(options.partition_path.display().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should be able to spawn `cryptsetup token import`")
+        .write_to_stdin(&json)
+        .wait_with_output()
+        .expect("should be able to wait on `cryptsetup token import`")
+        .ok_or(LuksTokenError::Import)
+}
+
+/// Save the enrolment metadata of a volume to its LUKS2 token.
+///
+/// If `key_slot` is `None` then the keyslots of the existing token are kept, and nothing is
+/// saved if there isn't one. Nothing is saved if the options don't set any key source, so a
+/// token isn't replaced by one that can't unlock the volume. Returns the keyslots the token
+/// was saved for.
+pub fn save_luks_token(
+    options: &Options,
+    key_slot: Option<u32>,
+) -> Result<Vec<u32>, Report<LuksTokenError>> {
+    if get_sources(options).is_empty() {
+        return Ok(Vec::new());
+    }
+    let existing = read_luks_token(options)?;
+    let keyslots = match (key_slot, &existing) {
+        (Some(slot), _) => vec![slot],
+        (None, Some((_, token))) => token.get_keyslots(),
+        (None, None) => Vec::new(),
+    };
+    if keyslots.is_empty() {
+        return Ok(keyslots);
+    }
+    let token = LuksToken::new(options, &keyslots);
+    write_luks_token(options, existing.map(|(id, _)| id), &token)?;
+    Ok(keyslots)
+}
+
+/// Remove a token from the LUKS2 header.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-token.8.html>
+pub fn remove_luks_token(options: &Options, id: u32) -> Result<(), Report<LuksTokenError>> {
+    Command::new("cryptsetup")
+        .arg("token")
+        .arg("remove")
+        .arg(format!("--token-id={id}"))
+        .arg(options.partition_path.display().to_string())
+        .output()
+        .expect("should be able to execute `cryptsetup token remove`")
+        .ok_or(LuksTokenError::Remove)
+        .attach_key_value("Token", &id.to_string())
+}
+
+/// Fill the key options that are not set from the LUKS2 token.
+pub fn apply_luks_token(options: &Options) -> Result<Options, Report<LuksTokenError>> {
+    let mut options = options.clone();
+    if let Some((id, token)) = read_luks_token(&options)? {
+        trace!(id, "Reading key sources from LUKS2 token");
+        token.apply(&mut options);
+    }
+    Ok(options)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum LuksTokenError {
+    #[error("Unable to read LUKS2 header, LUKS1 does not support tokens")]
+    Dump,
+    #[error("Unable to deserialize LUKS2 token")]
+    Deserialize,
+    #[error("Unsupported LUKS2 token version")]
+    Version,
+    #[error("Unable to import LUKS2 token")]
+    Import,
+    #[error("Unable to remove LUKS2 token")]
+    Remove,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn luks_token_round_trip() {
+        // Arrange
+        let options = Options {
+            key_path: Some(PathBuf::from("/root/.config/mount-luks/e.key")),
+            key_prompt: Some(true),
+            tpm_handle: Some(TpmHandle::from(
+                "0x81000001"
+                    .parse::<PersistentHandle>()
+                    .expect("Should be able to parse handle"),
+            )),
+            ..Options::default()
+        };
+        let token = LuksToken::new(&options, &[1]);
+        let metadata = serde_json::json!({
+            "keyslots": {},
+            "tokens": {
+                "0": { "type": "systemd-tpm2", "keyslots": ["0"] },
+                "2": token,
+            },
+        });
+
+        // Act
+        let (id, found) = find_token(&metadata)
+            .expect("Should be able to find token")
+            .expect("Should have a token");
+        let mut applied = Options::default();
+        found.apply(&mut applied);
+
+        // Assert
+        assert_eq!(id, 2);
+        assert_eq!(found, token);
+        assert_eq!(found.get_keyslots(), vec![1]);
+        assert_eq!(found.pcrs, Some(vec![7]));
+        assert_eq!(applied.key_path, options.key_path);
+        assert_eq!(applied.key_prompt, Some(true));
+        assert_eq!(applied.tpm_handle, options.tpm_handle);
+        assert_eq!(
+            get_sources(&applied),
+            vec![KeySource::File, KeySource::Tpm, KeySource::Prompt]
+        );
+    }
+}
@@ -3,13 +3,34 @@ mod check_if_mounted;
 mod check_key;
 mod check_mount_exists;
 mod check_partition_exists;
+mod check_removable;
+mod credential;
+mod derive_key;
 mod get_key;
 mod is_luks;
 mod is_partition_locked;
+mod key_device;
+mod key_file;
+mod key_shares;
+mod key_slots;
+mod key_source;
+mod keyring;
+mod lock_luks;
+mod luks_backend;
+mod luks_token;
+mod mount_all_command;
 mod mount_command;
 mod mount_partition;
+#[cfg(feature = "native-luks")]
+mod native_luks_backend;
+mod open_key_file;
+mod remove_luks_command;
+mod rotate_luks_command;
 mod set_luks_command;
+mod status_command;
 mod unlock_luks;
+mod unmount_command;
+mod unmount_partition;
 mod validate_command;
 
 pub use add_key::*;
@@ -17,11 +38,32 @@ pub use check_if_mounted::*;
 pub use check_key::*;
 pub use check_mount_exists::*;
 pub use check_partition_exists::*;
+pub use check_removable::*;
+pub use credential::*;
+pub use derive_key::*;
 pub use get_key::*;
 pub use is_luks::*;
 pub use is_partition_locked::*;
+pub use key_device::*;
+pub use key_file::*;
+pub use key_shares::*;
+pub use key_slots::*;
+pub use key_source::*;
+pub use keyring::*;
+pub use lock_luks::*;
+pub use luks_backend::*;
+pub use luks_token::*;
+pub use mount_all_command::*;
 pub use mount_command::*;
 pub use mount_partition::*;
+#[cfg(feature = "native-luks")]
+pub use native_luks_backend::*;
+pub use open_key_file::*;
+pub use remove_luks_command::*;
+pub use rotate_luks_command::*;
 pub use set_luks_command::*;
+pub use status_command::*;
 pub use unlock_luks::*;
+pub use unmount_command::*;
+pub use unmount_partition::*;
 pub use validate_command::*;
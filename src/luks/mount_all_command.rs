@@ -0,0 +1,60 @@
+use crate::prelude::*;
+use strum::Display;
+
+/// Unlock and mount every configured volume in turn.
+///
+/// Volumes that are already mounted are skipped. A failure to mount one volume does not
+/// prevent the remaining volumes from being mounted.
+pub fn mount_all_command(volumes: Vec<Options>) -> Result<(), AnyReport> {
+    let mut outcomes = Vec::new();
+    for options in volumes {
+        let name = options.name.clone();
+        let outcome = if is_mounted(&options) {
+            info!("Skipping {name} as it is already mounted");
+            MountOutcome::Skipped
+        } else {
+            if options.no_header != Some(true) {
+                print_header(&options, SubCommand::Mount { all: true });
+            }
+            match mount_command(options) {
+                Ok(()) => MountOutcome::Mounted,
+                Err(report) => {
+                    print_error(&format!("Unable to mount {name}"));
+                    eprintln!("\n{report}");
+                    MountOutcome::Failed
+                }
+            }
+        };
+        outcomes.push((name, outcome));
+    }
+    let rows: Vec<Vec<String>> = outcomes
+        .iter()
+        .map(|(name, outcome)| vec![name.clone(), outcome.to_string()])
+        .collect();
+    eprintln!();
+    print_table(&["Volume", "Result"], &rows);
+    let failed = outcomes
+        .iter()
+        .filter(|(_, outcome)| *outcome == MountOutcome::Failed)
+        .count();
+    if failed > 0 {
+        let report = Report::new(MountAllError)
+            .attach_key_value("Failed", &format!("{failed} of {}", outcomes.len()));
+        return Err(report.into());
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum MountOutcome {
+    #[strum(to_string = "mounted")]
+    Mounted,
+    #[strum(to_string = "skipped as already mounted")]
+    Skipped,
+    #[strum(to_string = "failed")]
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[error("Failed to mount every volume")]
+pub struct MountAllError;
@@ -17,7 +17,7 @@ pub fn mount_command(options: Options) -> Result<(), AnyReport> {
         total_steps,
         "Checking if partition is encrypted with LUKS",
     );
-    is_luks_partition(&options)?;
+    luks_backend().is_luks(&options)?;
     print_step_completed("Partition is encrypted with LUKS");
 
     print_step_start(
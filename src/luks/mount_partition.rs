@@ -21,7 +21,7 @@ mod tests {
     #[test]
     fn _mount_partition() {
         // Arrange
-        let options = Options::read_options(None).expect("Should be able to read options");
+        let options = Options::read_options(None, None).expect("Should be able to read options");
 
         // Act
         let result = mount_partition(&options);
@@ -0,0 +1,156 @@
+use crate::prelude::*;
+use libcryptsetup_rs::consts::flags::CryptActivate;
+use libcryptsetup_rs::consts::vals::{EncryptionFormat, KeyslotInfo};
+use libcryptsetup_rs::{CryptDevice, CryptInit, CryptKeyslotHandle, LibcryptErr};
+use nix::errno::Errno;
+
+/// LUKS backend that uses libcryptsetup.
+///
+/// Failures are reported from the library error codes so an incorrect key can be told apart
+/// from a busy device, and the key is only run through the KDF once when unlocking.
+///
+/// - <https://docs.rs/libcryptsetup-rs/>
+pub struct NativeLuksBackend;
+
+impl LuksBackend for NativeLuksBackend {
+    fn is_luks(&self, options: &Options) -> Result<(), Report<IsLuksError>> {
+        let mut device = CryptInit::init(&options.partition_path)
+            .change_context(IsLuksError::Unexpected)
+            .attach_path(&options.partition_path)?;
+        if device.context_handle().load::<()>(None, None).is_err() {
+            bail!(IsLuksError::NotLuks);
+        }
+        match device.format_handle().get_type() {
+            Ok(EncryptionFormat::Luks1 | EncryptionFormat::Luks2) => Ok(()),
+            Ok(_) => Err(Report::new(IsLuksError::NotLuks)),
+            Err(error) => Err(Report::new(error).change_context(IsLuksError::Unexpected)),
+        }
+    }
+
+    fn check_key(&self, options: &Options, key: &Secret) -> Result<(), Report<KeyError>> {
+        let mut device = load_device(options)?;
+        device
+            .activate_handle()
+            .activate_by_passphrase(None, None, key.expose(), CryptActivate::empty())
+            .map_err(|error| to_report(error, KeyError::InvalidKey))?;
+        Ok(())
+    }
+
+    fn find_key_slot(&self, options: &Options, key: &Secret) -> Result<u32, Report<KeyError>> {
+        let mut device = load_device(options)?;
+        device
+            .activate_handle()
+            .activate_by_passphrase(None, None, key.expose(), CryptActivate::empty())
+            .map_err(|error| to_report(error, KeyError::InvalidKey))
+    }
+
+    fn unlock(&self, options: &Options, key: &Secret) -> Result<(), Report<KeyError>> {
+        let mut device = load_device(options)?;
+        device
+            .activate_handle()
+            .activate_by_passphrase(
+                Some(&options.mapper_name),
+                None,
+                key.expose(),
+                CryptActivate::empty(),
+            )
+            .map_err(|error| to_report(error, KeyError::Unlock))
+            .attach_path(&options.get_mapper_path())?;
+        Ok(())
+    }
+
+    fn add_key(
+        &self,
+        options: &Options,
+        existing: &Secret,
+        key: &Secret,
+        key_slot: Option<u32>,
+    ) -> Result<u32, Report<KeyError>> {
+        let mut device = load_device(options)?;
+        let slot = device
+            .keyslot_handle()
+            .add_by_passphrase(key_slot, existing.expose(), key.expose())
+            .map_err(|error| to_report(error, KeyError::Add))?;
+        debug!(slot, "Added key to LUKS keyslot");
+        Ok(slot)
+    }
+
+    fn get_key_slots(&self, options: &Options) -> Result<Vec<u32>, Report<KeySlotError>> {
+        let mut device = load_device(options).change_context(KeySlotError::Dump)?;
+        let format = device
+            .format_handle()
+            .get_type()
+            .change_context(KeySlotError::Dump)?;
+        let max = CryptKeyslotHandle::max_keyslots(format).change_context(KeySlotError::Dump)?;
+        let mut slots = Vec::new();
+        for slot in 0..max {
+            let status = device
+                .keyslot_handle()
+                .status(slot)
+                .change_context(KeySlotError::Dump)?;
+            if matches!(status, KeyslotInfo::Active | KeyslotInfo::ActiveLast) {
+                slots.push(slot);
+            }
+        }
+        Ok(slots)
+    }
+
+    fn remove_key_slot(&self, options: &Options, slot: u32) -> Result<(), Report<KeySlotError>> {
+        let mut device = load_device(options).change_context(KeySlotError::Remove)?;
+        device
+            .keyslot_handle()
+            .destroy(slot)
+            .change_context(KeySlotError::Remove)
+            .attach_key_value("Keyslot", &slot.to_string())
+    }
+}
+
+fn load_device(options: &Options) -> Result<CryptDevice, Report<KeyError>> {
+    let mut device = CryptInit::init(&options.partition_path)
+        .map_err(|error| to_report(error, KeyError::Unlock))
+        .attach_path(&options.partition_path)?;
+    device
+        .context_handle()
+        .load::<()>(None, None)
+        .map_err(|error| to_report(error, KeyError::Unlock))
+        .attach_path(&options.partition_path)?;
+    Ok(device)
+}
+
+/// Convert a libcryptsetup error, using the error code to distinguish an incorrect key
+/// and a busy device from other failures.
+fn to_report(error: LibcryptErr, default: KeyError) -> Report<KeyError> {
+    let context = match &error {
+        LibcryptErr::IOError(io) => match io.raw_os_error().map(Errno::from_raw) {
+            Some(Errno::EPERM) => KeyError::InvalidKey,
+            Some(Errno::EBUSY | Errno::EEXIST) => KeyError::Busy,
+            _ => default,
+        },
+        _ => default,
+    };
+    Report::new(error).change_context(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Error as IoError;
+
+    #[test]
+    fn _to_report() {
+        // Arrange
+        let invalid_key = LibcryptErr::IOError(IoError::from_raw_os_error(Errno::EPERM as i32));
+        let busy = LibcryptErr::IOError(IoError::from_raw_os_error(Errno::EBUSY as i32));
+        let other = LibcryptErr::IOError(IoError::from_raw_os_error(Errno::EINVAL as i32));
+
+        // Act
+        let invalid_key = to_report(invalid_key, KeyError::Unlock);
+        let busy = to_report(busy, KeyError::Unlock);
+        let other = to_report(other, KeyError::Unlock);
+
+        // Assert
+        assert_eq!(invalid_key.current_context(), &KeyError::InvalidKey);
+        assert_eq!(busy.current_context(), &KeyError::Busy);
+        assert_eq!(other.current_context(), &KeyError::Unlock);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::prelude::*;
+use nix::fcntl::{AT_FDCWD, AtFlags, OFlag, openat, readlinkat};
+use nix::sys::stat::{FileStat, Mode, SFlag, fstat, fstatat};
+use std::collections::VecDeque;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::os::fd::{AsFd, OwnedFd};
+use std::path::{Component, absolute};
+
+/// Permission bits for group and others.
+const GROUP_OTHER_MODE: u32 = 0o077;
+
+/// Permission bits for group and others to write.
+const GROUP_OTHER_WRITE_MODE: u32 = 0o022;
+
+/// Sticky bit, which stops users from renaming or removing the entries of other users.
+const STICKY_MODE: u32 = 0o1000;
+
+/// Maximum number of symlinks followed while resolving the path, the same as the kernel.
+const MAX_SYMLINKS: usize = 40;
+
+/// Name of the parent directory component.
+const PARENT_DIR: &str = "..";
+
+/// Open the key file after checking only root can change it.
+///
+/// The path is resolved one component at a time relative to the directory opened before it
+/// and the file is opened with `O_NOFOLLOW`, so it can't be redirected between the checks and
+/// reading the returned file.
+///
+/// Every directory on the way must be owned by root and not writable by group or others
+/// unless the sticky bit is set. Every symlink must be owned by root. The file must be owned
+/// by root with no group or other permissions.
+pub fn open_key_file(path: &Path) -> Result<File, Report<KeyError>> {
+    let absolute_path = absolute(path)
+        .change_context(KeyError::KeyFile)
+        .attach_path(path)?;
+    let root = Path::new("/");
+    let root_fd = open_dir(AT_FDCWD, root.as_os_str(), root)?;
+    let mut checked = vec![(
+        root.to_owned(),
+        fstat(&root_fd).change_context(KeyError::KeyFile)?,
+    )];
+    let mut dirs = vec![(root_fd, root.to_owned())];
+    let mut remaining = get_components(&absolute_path);
+    let mut symlinks = 0;
+    while let Some(name) = remaining.pop_front() {
+        if name == PARENT_DIR {
+            if dirs.len() > 1 {
+                dirs.pop();
+            }
+            continue;
+        }
+        let (dir, dir_path) = dirs.last().expect("Root directory should always be open");
+        let entry_path = dir_path.join(&name);
+        let stat = fstatat(dir, name.as_os_str(), AtFlags::AT_SYMLINK_NOFOLLOW)
+            .change_context(KeyError::KeyFile)
+            .attach_path(&entry_path)?;
+        if get_file_type(&stat) == SFlag::S_IFLNK {
+            symlinks += 1;
+            if symlinks > MAX_SYMLINKS {
+                return Err(Report::new(KeyError::KeyFile)
+                    .attach("Too many levels of symlinks")
+                    .attach_path(path));
+            }
+            let target = readlinkat(dir, name.as_os_str())
+                .change_context(KeyError::KeyFile)
+                .attach_path(&entry_path)?;
+            let target = Path::new(&target);
+            if target.has_root() {
+                dirs.truncate(1);
+            }
+            for component in get_components(target).into_iter().rev() {
+                remaining.push_front(component);
+            }
+            checked.push((entry_path, stat));
+            continue;
+        }
+        if remaining.is_empty() {
+            let fd = openat(
+                dir,
+                name.as_os_str(),
+                OFlag::O_RDONLY | OFlag::O_NOFOLLOW | OFlag::O_CLOEXEC,
+                Mode::empty(),
+            )
+            .change_context(KeyError::KeyFile)
+            .attach_path(&entry_path)?;
+            let stat = fstat(&fd)
+                .change_context(KeyError::KeyFile)
+                .attach_path(&entry_path)?;
+            check_file(&stat, &entry_path).attach_path(path)?;
+            for (checked_path, stat) in &checked {
+                check_parent(stat, checked_path).attach_path(path)?;
+            }
+            return Ok(File::from(fd));
+        }
+        let fd = open_dir(dir, name.as_os_str(), &entry_path)?;
+        let stat = fstat(&fd)
+            .change_context(KeyError::KeyFile)
+            .attach_path(&entry_path)?;
+        checked.push((entry_path.clone(), stat));
+        dirs.push((fd, entry_path));
+    }
+    Err(Report::new(KeyError::KeyFile)
+        .attach("Key path is a directory")
+        .attach_path(path))
+}
+
+/// Open a directory without following a symlink, only to resolve the next component.
+fn open_dir<Fd: AsFd>(dir: Fd, name: &OsStr, path: &Path) -> Result<OwnedFd, Report<KeyError>> {
+    openat(
+        dir,
+        name,
+        OFlag::O_PATH | OFlag::O_DIRECTORY | OFlag::O_NOFOLLOW | OFlag::O_CLOEXEC,
+        Mode::empty(),
+    )
+    .change_context(KeyError::KeyFile)
+    .attach_path(path)
+}
+
+/// Split a path into the names to resolve, keeping `..` so it's applied after symlinks.
+fn get_components(path: &Path) -> VecDeque<OsString> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_owned()),
+            Component::ParentDir => Some(OsString::from(PARENT_DIR)),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+        })
+        .collect()
+}
+
+fn get_file_type(stat: &FileStat) -> SFlag {
+    SFlag::from_bits_truncate(stat.st_mode & SFlag::S_IFMT.bits())
+}
+
+fn check_file(stat: &FileStat, path: &Path) -> Result<(), Report<KeyError>> {
+    if stat.st_uid != 0 || stat.st_mode & GROUP_OTHER_MODE != 0 {
+        return Err(insecure(stat, path));
+    }
+    Ok(())
+}
+
+/// Check a directory or symlink on the way to the key file can't be changed by other users.
+fn check_parent(stat: &FileStat, path: &Path) -> Result<(), Report<KeyError>> {
+    let is_writable = get_file_type(stat) == SFlag::S_IFDIR
+        && stat.st_mode & GROUP_OTHER_WRITE_MODE != 0
+        && stat.st_mode & STICKY_MODE == 0;
+    if stat.st_uid != 0 || is_writable {
+        return Err(insecure(stat, path));
+    }
+    Ok(())
+}
+
+fn insecure(stat: &FileStat, path: &Path) -> Report<KeyError> {
+    Report::new(KeyError::InsecureKeyFile {
+        path: path.to_owned(),
+        uid: stat.st_uid,
+        mode: stat.st_mode & 0o7777,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{Permissions, create_dir, set_permissions, write};
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    #[test]
+    fn open_key_file_readable_by_others() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("test.key");
+        write(&path, "key").expect("Should be able to write key file");
+        set_permissions(&path, Permissions::from_mode(0o644))
+            .expect("Should be able to set permissions");
+
+        // Act
+        let result = open_key_file(&path);
+
+        // Assert
+        let report = result.expect_err("Should reject key file");
+        assert!(matches!(
+            report.current_context(),
+            KeyError::InsecureKeyFile { mode: 0o644, .. }
+        ));
+    }
+
+    #[test]
+    fn open_key_file_private() {
+        assert!(is_root().is_ok(), "Root is required to run this test");
+
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("test.key");
+        write(&path, "key").expect("Should be able to write key file");
+        set_permissions(&path, Permissions::from_mode(0o600))
+            .expect("Should be able to set permissions");
+
+        // Act
+        let result = open_key_file(&path);
+
+        // Assert
+        result.expect("Should be able to open private key file");
+    }
+
+    #[test]
+    fn open_key_file_symlink_to_world_writable_dir() {
+        assert!(is_root().is_ok(), "Root is required to run this test");
+
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let shared = dir.join("shared");
+        create_dir(&shared).expect("Should be able to create dir");
+        set_permissions(&shared, Permissions::from_mode(0o777))
+            .expect("Should be able to set permissions");
+        let target = shared.join("test.key");
+        write(&target, "key").expect("Should be able to write key file");
+        set_permissions(&target, Permissions::from_mode(0o600))
+            .expect("Should be able to set permissions");
+        let path = dir.join("test.key");
+        symlink(&target, &path).expect("Should be able to create symlink");
+
+        // Act
+        let result = open_key_file(&path);
+
+        // Assert
+        let report = result.expect_err("Should reject symlink target");
+        assert!(matches!(
+            report.current_context(),
+            KeyError::InsecureKeyFile { path, mode: 0o777, .. } if *path == shared
+        ));
+    }
+
+    #[test]
+    fn open_key_file_in_symlinked_world_writable_dir() {
+        assert!(is_root().is_ok(), "Root is required to run this test");
+
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let shared = dir.join("shared");
+        create_dir(&shared).expect("Should be able to create dir");
+        set_permissions(&shared, Permissions::from_mode(0o777))
+            .expect("Should be able to set permissions");
+        let keys = shared.join("keys");
+        create_dir(&keys).expect("Should be able to create dir");
+        write(keys.join("test.key"), "key").expect("Should be able to write key file");
+        set_permissions(keys.join("test.key"), Permissions::from_mode(0o600))
+            .expect("Should be able to set permissions");
+        let link = dir.join("keys");
+        symlink(&keys, &link).expect("Should be able to create symlink");
+
+        // Act
+        let result = open_key_file(&link.join("test.key"));
+
+        // Assert
+        let report = result.expect_err("Should reject directory reached through symlink");
+        assert!(matches!(
+            report.current_context(),
+            KeyError::InsecureKeyFile { path, mode: 0o777, .. } if *path == shared
+        ));
+    }
+
+    #[test]
+    fn insecure_key_file_message() {
+        // Arrange
+        let error = KeyError::InsecureKeyFile {
+            path: PathBuf::from("/etc/mount-luks/test.key"),
+            uid: 1000,
+            mode: 0o644,
+        };
+
+        // Act
+        let message = error.to_string();
+
+        // Assert
+        assert_eq!(
+            message,
+            "Key file is not private to root, /etc/mount-luks/test.key has owner 1000 and mode 644"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::prelude::*;
+
+pub fn remove_luks_command(options: Options) -> Result<(), AnyReport> {
+    let counter = Mutex::new(0);
+    let total_steps = 7;
+    let backend = luks_backend();
+
+    print_step_start(&counter, total_steps, "Checking if root");
+    is_root()?;
+    print_step_completed("Access granted");
+
+    print_step_start(&counter, total_steps, "Checking if partition exists");
+    check_partition_exist(&options)?;
+    print_step_completed("Partition exists");
+
+    print_step_start(
+        &counter,
+        total_steps,
+        "Checking if partition is encrypted with LUKS",
+    );
+    backend.is_luks(&options)?;
+    print_step_completed("Partition is encrypted with LUKS");
+
+    print_step_start(&counter, total_steps, "Finding mount-luks keyslot");
+    let token = read_luks_token(&options).unwrap_or_else(|report| {
+        debug!("Unable to read LUKS2 token:\n{report:?}");
+        None
+    });
+    let slot = get_owned_key_slot(&options, token.as_ref().map(|(_, token)| token))?;
+    print_step_completed(&format!("Keyslot {slot} belongs to mount-luks"));
+
+    print_step_start(&counter, total_steps, "Checking another keyslot remains");
+    check_removable_key_slot(&backend.get_key_slots(&options)?, slot)?;
+    print_step_completed("Another keyslot can still unlock the partition");
+
+    print_step_start(&counter, total_steps, "Removing LUKS keyslot");
+    let is_confirmed =
+        prompt_confirm(&format!("Remove keyslot {slot}?")).change_context(KeySlotError::Remove)?;
+    if !is_confirmed {
+        return Err(Report::new(KeySlotError::Remove).attach("Cancelled").into());
+    }
+    backend.remove_key_slot(&options, slot)?;
+    print_step_completed(&format!("Removed keyslot {slot}"));
+
+    print_step_start(&counter, total_steps, "Removing enrolment metadata");
+    if let Some((id, token)) = token
+        && token.get_keyslots().contains(&slot)
+        && let Err(report) = remove_luks_token(&options, id)
+    {
+        warn!("Unable to remove LUKS2 token:\n{report:?}");
+    }
+    if options.key_slot.is_some() {
+        options.save_option("key_slot", "null")?;
+    }
+    print_step_completed("Removed keyslot from LUKS2 token and options file");
+
+    Ok(())
+}
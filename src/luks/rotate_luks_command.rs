@@ -0,0 +1,131 @@
+use crate::prelude::*;
+
+/// Replace the mount-luks keyslot with a key built from the current key sources.
+///
+/// The steps are ordered so at least one keyslot can unlock the partition if the command is
+/// interrupted. The new key is added and checked before the old keyslot is removed, and
+/// running the command again after an interruption picks up the key that was already added.
+pub fn rotate_luks_command(options: Options) -> Result<(), AnyReport> {
+    let counter = Mutex::new(0);
+    let total_steps = 9;
+    let backend = luks_backend();
+
+    print_step_start(&counter, total_steps, "Checking if root");
+    is_root()?;
+    print_step_completed("Access granted");
+
+    print_step_start(&counter, total_steps, "Checking if partition exists");
+    check_partition_exist(&options)?;
+    print_step_completed("Partition exists");
+
+    print_step_start(
+        &counter,
+        total_steps,
+        "Checking if partition is encrypted with LUKS",
+    );
+    backend.is_luks(&options)?;
+    print_step_completed("Partition is encrypted with LUKS");
+
+    print_step_start(&counter, total_steps, "Finding current mount-luks keyslot");
+    let token = read_luks_token(&options).unwrap_or_else(|report| {
+        debug!("Unable to read LUKS2 token:\n{report:?}");
+        None
+    });
+    let old_slot = get_owned_key_slot(&options, token.as_ref().map(|(_, token)| token))?;
+    print_step_completed(&format!("Keyslot {old_slot} belongs to mount-luks"));
+
+    print_step_start(&counter, total_steps, "Building new key");
+    let (key, shares) = match options.key_threshold {
+        Some(threshold) => {
+            let (key, shares) = split_threshold_key(&options, threshold)?;
+            (key, Some(shares))
+        }
+        None => (get_key(&options)?, None),
+    };
+    let path = options.get_key_shares_path();
+    let staged = shares
+        .as_ref()
+        .map(|shares| stage_key_shares(&path, shares))
+        .transpose()
+        .change_context(KeyError::Shares)?;
+    let discard_staged = || {
+        if let Some(staged) = &staged {
+            discard_key_shares(staged);
+        }
+    };
+    print_step_completed(&format!("Built new key of {} bytes", key.len()));
+
+    print_step_start(&counter, total_steps, "Adding new LUKS key");
+    let (new_slot, added) = if shares.is_none() && backend.check_key(&options, &key).is_ok() {
+        let slot = backend.find_key_slot(&options, &key)?;
+        if slot == old_slot {
+            return Err(Report::new(KeyError::Exists)
+                .attach(format!(
+                    "Key has not changed since it was added to keyslot {slot}"
+                ))
+                .into());
+        }
+        print_step_completed(&format!("Skipped as new key is already in keyslot {slot}"));
+        (slot, false)
+    } else {
+        let slot = prompt_existing_passphrase()
+            .and_then(|existing| backend.add_key(&options, &existing, &key, None))
+            .inspect_err(|_| discard_staged())?;
+        print_step_completed(&format!("Added new LUKS key to keyslot {slot}"));
+        (slot, true)
+    };
+
+    print_step_start(&counter, total_steps, "Checking new key");
+    check_new_key(&options, &key, staged.as_deref(), &path)
+        .inspect_err(|_| {
+            if added {
+                remove_added_key_slot(&options, new_slot);
+            }
+            discard_staged();
+        })
+        .attach(format!(
+            "Keyslot {new_slot} can't be used, the old keyslot {old_slot} is kept"
+        ))?;
+    print_step_completed("New key unlocks the partition");
+
+    print_step_start(&counter, total_steps, "Removing old LUKS keyslot");
+    let active = backend.get_key_slots(&options)?;
+    if active.contains(&old_slot) {
+        check_removable_key_slot(&active, old_slot)?;
+        backend.remove_key_slot(&options, old_slot)?;
+        print_step_completed(&format!("Removed keyslot {old_slot}"));
+    } else {
+        print_step_completed(&format!(
+            "Skipped as keyslot {old_slot} was already removed"
+        ));
+    }
+
+    print_step_start(&counter, total_steps, "Saving keyslot");
+    options.save_option("key_slot", &new_slot.to_string())?;
+    match save_luks_token(&options, Some(new_slot)) {
+        Ok(keyslots) if keyslots.is_empty() => {
+            print_step_completed("Saved keyslot to options file");
+        }
+        Ok(_) => print_step_completed("Saved keyslot to options file and LUKS2 token"),
+        Err(report) => {
+            warn!("Unable to save LUKS2 token:\n{report:?}");
+            print_step_completed("Saved keyslot to options file");
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the new key unlocks the partition and move its staged shares file into place.
+fn check_new_key(
+    options: &Options,
+    key: &Secret,
+    staged: Option<&Path>,
+    path: &Path,
+) -> Result<(), Report<KeyError>> {
+    luks_backend().check_key(options, key)?;
+    if let Some(staged) = staged {
+        commit_key_shares(staged, path).change_context(KeyError::Shares)?;
+    }
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use crate::prelude::*;
 
-pub fn set_luks_command(options: Options) -> Result<(), AnyReport> {
+pub fn set_luks_command(options: Options, key_slot: Option<u32>) -> Result<(), AnyReport> {
     let counter = Mutex::new(0);
-    let total_steps = 4;
+    let total_steps = 6;
 
     print_step_start(&counter, total_steps, "Checking if root");
     is_root()?;
@@ -17,12 +17,42 @@ pub fn set_luks_command(options: Options) -> Result<(), AnyReport> {
         total_steps,
         "Checking if partition is encrypted with LUKS",
     );
-    is_luks_partition(&options)?;
+    luks_backend().is_luks(&options)?;
     print_step_completed("Partition is encrypted with LUKS");
 
     print_step_start(&counter, total_steps, "Adding LUKS key");
-    add_key(&options)?;
-    print_step_completed("Added LUKS key");
+    let key_slot = add_key(&options, key_slot)?;
+    print_step_completed(&format!("Added LUKS key to keyslot {key_slot}"));
+
+    print_step_start(&counter, total_steps, "Saving keyslot");
+    if options.key_slot == Some(key_slot) {
+        print_step_completed("Skipped as keyslot was already saved");
+    } else {
+        if let Some(previous) = options.key_slot {
+            warn!(
+                previous,
+                "Previous keyslot is not removed, remove it with `cryptsetup luksKillSlot`"
+            );
+        }
+        options.save_option("key_slot", &key_slot.to_string())?;
+        print_step_completed("Saved keyslot to options file");
+    }
+
+    print_step_start(
+        &counter,
+        total_steps,
+        "Saving enrolment metadata to LUKS2 token",
+    );
+    match save_luks_token(&options, Some(key_slot)) {
+        Ok(keyslots) if keyslots.is_empty() => {
+            print_step_completed("Skipped as no key source is set");
+        }
+        Ok(_) => print_step_completed("Saved enrolment metadata to LUKS2 token"),
+        Err(report) => {
+            warn!("Unable to save LUKS2 token:\n{report:?}");
+            print_step_completed("Skipped as the LUKS2 token could not be saved");
+        }
+    }
 
     Ok(())
 }
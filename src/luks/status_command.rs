@@ -0,0 +1,239 @@
+use crate::prelude::*;
+use strum::Display;
+
+const HEADERS: [&str; 7] = [
+    "Volume",
+    "Partition",
+    "LUKS",
+    "Unlocked",
+    "Mounted",
+    "TPM handle",
+    "Shares",
+];
+
+/// Report the state of each configured volume without making any changes.
+///
+/// Checks that require root are skipped when not running as root.
+pub fn status_command(volumes: &[Options]) {
+    let root = is_root().is_ok();
+    if !root {
+        warn!("Not running as root so some checks will be skipped");
+    }
+    let is_tpm_used = volumes
+        .iter()
+        .any(|options| options.get_tpm_handle().is_some());
+    let handles = (root && is_tpm_used)
+        .then(|| tpm_backend().get_handles())
+        .and_then(|result| {
+            result
+                .inspect_err(|report| warn!("Unable to check TPM handles:\n{report:?}"))
+                .ok()
+        });
+    let rows: Vec<Vec<String>> = volumes
+        .iter()
+        .map(|options| get_volume_status(options, root, handles.as_deref()).to_row())
+        .collect();
+    print_table(&HEADERS, &rows);
+}
+
+/// Run the read-only checks against a volume.
+///
+/// `handles` is `None` when not running as root or the persistent TPM handles could not be
+/// queried.
+#[must_use]
+pub fn get_volume_status(
+    options: &Options,
+    root: bool,
+    handles: Option<&[PersistentHandle]>,
+) -> VolumeStatus {
+    let partition = CheckStatus::from(check_partition_exist(options).is_ok());
+    let luks = if partition == CheckStatus::No {
+        CheckStatus::No
+    } else if !root {
+        CheckStatus::RootRequired
+    } else {
+        match luks_backend().is_luks(options) {
+            Ok(()) => CheckStatus::Yes,
+            Err(report) => match report.current_context() {
+                IsLuksError::NotLuks => CheckStatus::No,
+                IsLuksError::Unexpected => CheckStatus::Failed,
+            },
+        }
+    };
+    let unlocked = CheckStatus::from(is_partition_locked(options).is_err());
+    let mounted = CheckStatus::from(is_mounted(options));
+    let tpm_handle = match (options.get_tpm_handle(), handles) {
+        (None, _) => CheckStatus::NotConfigured,
+        (Some(_), None) if !root => CheckStatus::RootRequired,
+        (Some(_), None) => CheckStatus::Failed,
+        (Some(handle), Some(handles)) => CheckStatus::from(handles.contains(&handle)),
+    };
+    let shares = get_shares_status(options, root, handles);
+    VolumeStatus {
+        name: options.name.clone(),
+        partition,
+        luks,
+        unlocked,
+        mounted,
+        tpm_handle,
+        shares,
+    }
+}
+
+/// Count the key sources that have a share and look available without reading them.
+fn get_shares_status(
+    options: &Options,
+    root: bool,
+    handles: Option<&[PersistentHandle]>,
+) -> CheckStatus {
+    if options.key_threshold.is_none() {
+        return CheckStatus::NotConfigured;
+    }
+    if !root {
+        return CheckStatus::RootRequired;
+    }
+    let key_shares = match read_key_shares(&options.get_key_shares_path()) {
+        Ok(key_shares) => key_shares,
+        Err(report) => {
+            warn!("Unable to read shares file:\n{report:?}");
+            return CheckStatus::Failed;
+        }
+    };
+    let sources = get_sources(options);
+    let available = sources
+        .iter()
+        .filter(|source| key_shares.shares.contains_key(source))
+        .filter(|&&source| is_source_available(options, source, handles))
+        .count();
+    CheckStatus::Shares {
+        available,
+        total: sources.len(),
+        threshold: key_shares.threshold,
+    }
+}
+
+fn is_source_available(
+    options: &Options,
+    source: KeySource,
+    handles: Option<&[PersistentHandle]>,
+) -> bool {
+    match source {
+        KeySource::File => match (&options.key_device, &options.key_path) {
+            (Some(device), _) => device.get_path().exists(),
+            (None, Some(path)) => path.is_file(),
+            (None, None) => false,
+        },
+        KeySource::Tpm => match (options.get_tpm_handle(), handles) {
+            (Some(handle), Some(handles)) => handles.contains(&handle),
+            _ => false,
+        },
+        KeySource::Prompt => true,
+        KeySource::Keyring => options.keyring.as_deref().is_some_and(has_keyring_key),
+        KeySource::Credential => options.credential.as_deref().is_some_and(has_credential),
+        KeySource::Tang => options.get_tang_binding_path().is_file(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VolumeStatus {
+    pub name: String,
+    pub partition: CheckStatus,
+    pub luks: CheckStatus,
+    pub unlocked: CheckStatus,
+    pub mounted: CheckStatus,
+    pub tpm_handle: CheckStatus,
+    pub shares: CheckStatus,
+}
+
+impl VolumeStatus {
+    fn to_row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.partition.to_string(),
+            self.luks.to_string(),
+            self.unlocked.to_string(),
+            self.mounted.to_string(),
+            self.tpm_handle.to_string(),
+            self.shares.to_string(),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum CheckStatus {
+    #[strum(to_string = "yes")]
+    Yes,
+    #[strum(to_string = "no")]
+    No,
+    #[strum(to_string = "-")]
+    NotConfigured,
+    #[strum(to_string = "root required")]
+    RootRequired,
+    #[strum(to_string = "error")]
+    Failed,
+    #[strum(to_string = "{available} of {total} (need {threshold})")]
+    Shares {
+        available: usize,
+        total: usize,
+        threshold: u8,
+    },
+}
+
+impl From<bool> for CheckStatus {
+    fn from(value: bool) -> Self {
+        if value { Self::Yes } else { Self::No }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _get_volume_status() {
+        // Arrange
+        let options = Options::read_options(None, None).expect("Should be able to read options");
+
+        // Act
+        let status = get_volume_status(&options, false, None);
+
+        // Assert
+        assert_eq!(status.name, options.name);
+        let partition = CheckStatus::from(check_partition_exist(&options).is_ok());
+        assert_eq!(status.partition, partition);
+        let luks = if partition == CheckStatus::No {
+            CheckStatus::No
+        } else {
+            CheckStatus::RootRequired
+        };
+        assert_eq!(status.luks, luks);
+        let tpm_handle = if options.get_tpm_handle().is_some() {
+            CheckStatus::RootRequired
+        } else {
+            CheckStatus::NotConfigured
+        };
+        assert_eq!(status.tpm_handle, tpm_handle);
+        let shares = if options.key_threshold.is_some() {
+            CheckStatus::RootRequired
+        } else {
+            CheckStatus::NotConfigured
+        };
+        assert_eq!(status.shares, shares);
+    }
+
+    #[test]
+    fn check_status_shares() {
+        // Arrange
+        let status = CheckStatus::Shares {
+            available: 2,
+            total: 3,
+            threshold: 2,
+        };
+
+        // Act
+        let output = status.to_string();
+
+        // Assert
+        assert_eq!(output, "2 of 3 (need 2)");
+    }
+}
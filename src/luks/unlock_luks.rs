@@ -1,15 +1,20 @@
 use crate::prelude::*;
 use std::process::Stdio;
 
+/// Unlock the LUKS partition with the key from all configured sources.
+///
+/// The key is not checked separately as an incorrect key fails with [`KeyError::InvalidKey`]
+/// on both backends: `cryptsetup` exits with code 2 and libcryptsetup returns `EPERM`.
 pub fn unlock_luks(options: &Options) -> Result<(), Report<KeyError>> {
     let key = get_key(options)?;
-    check_key(options, &key)?;
-    unlock_luks_with_key(options, &key)?;
-    Ok(())
+    luks_backend().unlock(options, &key)
 }
 
-fn unlock_luks_with_key(options: &Options, key: &str) -> Result<(), Report<KeyError>> {
-    Command::new("cryptsetup")
+/// Unlock the LUKS partition and create the mapper device.
+///
+/// - <https://man7.org/linux/man-pages/man8/cryptsetup-open.8.html>
+pub fn unlock_luks_with_key(options: &Options, key: &Secret) -> Result<(), Report<KeyError>> {
+    let output = Command::new("cryptsetup")
         .arg("luksOpen")
         .arg("--key-file=-") // Read password from stdin
         .arg(options.partition_path.display().to_string())
@@ -19,8 +24,12 @@ fn unlock_luks_with_key(options: &Options, key: &str) -> Result<(), Report<KeyEr
         .stderr(Stdio::piped())
         .spawn()
         .expect("should be able to spawn `cryptsetup luksOpen`")
-        .write_to_stdin(key)
+        .write_to_stdin(key.expose())
         .wait_with_output()
-        .expect("should be able to wait on `cryptsetup luksOpen`")
-        .ok_or(KeyError::Unlock)
+        .expect("should be able to wait on `cryptsetup luksOpen`");
+    if output.status.success() {
+        return Ok(());
+    }
+    let error = KeyError::from_exit_status(output.status, KeyError::Unlock);
+    Err(Report::new(error).attach_response(output.to_response()))
 }
@@ -0,0 +1,40 @@
+use crate::prelude::*;
+
+pub fn unmount_command(options: Options) -> Result<(), AnyReport> {
+    let counter = Mutex::new(0);
+    let total_steps = 5;
+
+    print_step_start(&counter, total_steps, "Checking if root");
+    is_root()?;
+    print_step_completed("Access granted");
+
+    print_step_start(&counter, total_steps, "Checking if mounted");
+    let mounted = check_if_mounted(&options).is_err();
+    if mounted {
+        print_step_completed("Partition is mounted");
+    } else {
+        print_step_completed("Partition is not mounted");
+    }
+
+    print_step_start(&counter, total_steps, "Unmounting partition");
+    if mounted {
+        unmount_partition(&options)?;
+        print_step_completed("Partition unmounted successfully");
+    } else {
+        print_step_completed("Skipped as partition is not mounted");
+    }
+
+    print_step_start(&counter, total_steps, "Locking LUKS partition");
+    if is_partition_locked(&options).is_err() {
+        lock_luks(&options)?;
+        print_step_completed("Locked LUKS partition");
+    } else {
+        print_step_completed("Skipped as partition is already locked");
+    }
+
+    print_step_start(&counter, total_steps, "Checking if partition is locked");
+    is_partition_locked(&options)?;
+    print_step_completed("Partition is locked");
+
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use crate::prelude::*;
+
+pub fn unmount_partition(options: &Options) -> Result<(), Report<UnmountError>> {
+    Command::new("umount")
+        .arg(options.mount_path.display().to_string())
+        .output()
+        .expect("should be able to execute `umount`")
+        .ok_or(UnmountError)
+        .attach_path(&options.mount_path)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[error("Failed to unmount partition")]
+pub struct UnmountError;
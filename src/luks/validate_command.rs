@@ -11,7 +11,7 @@ pub fn validate_command(options: Options) -> Result<(), AnyReport> {
     print_step_start(&counter, total_steps, "Validating key");
     let key = get_key(&options)?;
     debug!("Key is {} characters", key.len());
-    check_key(&options, &key)?;
+    luks_backend().check_key(&options, &key)?;
     print_step_completed("Key is valid");
 
     Ok(())
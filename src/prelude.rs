@@ -1,6 +1,7 @@
 pub use crate::cli::*;
 pub(crate) use crate::extensions::*;
 pub(crate) use crate::luks::*;
+pub(crate) use crate::tang::*;
 pub(crate) use crate::tpm::*;
 pub(crate) use crate::utils::*;
 pub(crate) use error_stack::ResultExt;
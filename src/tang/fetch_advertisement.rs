@@ -0,0 +1,194 @@
+use crate::prelude::*;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use p521::ecdsa::signature::Verifier;
+use p521::ecdsa::{Signature, VerifyingKey};
+use serde::Deserialize;
+
+/// Algorithm of the advertisement signatures.
+const SIGNATURE_ALGORITHM: &str = "ES512";
+
+/// Operation of the keys that sign the advertisement.
+const VERIFY_OPERATION: &str = "verify";
+
+/// Algorithm of the exchange key.
+pub const EXCHANGE_ALGORITHM: &str = "ECMR";
+
+/// Operation of the exchange key.
+pub const DERIVE_OPERATION: &str = "deriveKey";
+
+/// Keys advertised by a Tang server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Advertisement {
+    pub keys: Vec<Jwk>,
+}
+
+impl Advertisement {
+    /// Get the keys that sign the advertisement.
+    #[must_use]
+    pub fn get_signing_keys(&self) -> Vec<&Jwk> {
+        self.keys
+            .iter()
+            .filter(|key| key.has_operation(VERIFY_OPERATION))
+            .collect()
+    }
+
+    /// Get the key used for the McCallum-Relyea exchange.
+    #[must_use]
+    pub fn get_exchange_key(&self) -> Option<&Jwk> {
+        self.keys.iter().find(|key| {
+            key.alg.as_deref() == Some(EXCHANGE_ALGORITHM) && key.has_operation(DERIVE_OPERATION)
+        })
+    }
+}
+
+/// JSON Web Signature in either the general or the flattened JSON serialization.
+///
+/// - <https://datatracker.ietf.org/doc/html/rfc7515#section-7.2>
+#[derive(Deserialize)]
+struct Jws {
+    payload: String,
+    #[serde(default)]
+    signatures: Vec<JwsSignature>,
+    protected: Option<String>,
+    signature: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwsSignature {
+    protected: String,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct JwsHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// Fetch the advertisement of a Tang server.
+///
+/// - <https://github.com/latchset/tang#provisioning>
+pub fn fetch_advertisement(url: &str) -> Result<Advertisement, Report<AdvertisementError>> {
+    let url = format!("{}/adv", url.trim_end_matches('/'));
+    let body = get_tang_agent()
+        .get(&url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .change_context(AdvertisementError::Request)
+        .attach_key_value("URL", &url)?;
+    parse_advertisement(&body).attach_key_value("URL", &url)
+}
+
+/// Parse an advertisement and verify it's signed by every signing key it advertises.
+pub fn parse_advertisement(body: &str) -> Result<Advertisement, Report<AdvertisementError>> {
+    let jws: Jws = serde_json::from_str(body).change_context(AdvertisementError::Deserialize)?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(&jws.payload)
+        .change_context(AdvertisementError::Deserialize)?;
+    let keys: JwkSet =
+        serde_json::from_slice(&payload).change_context(AdvertisementError::Deserialize)?;
+    let mut signatures = jws.signatures;
+    if let (Some(protected), Some(signature)) = (jws.protected, jws.signature) {
+        signatures.push(JwsSignature {
+            protected,
+            signature,
+        });
+    }
+    let advertisement = Advertisement { keys: keys.keys };
+    let signing_keys = advertisement.get_signing_keys();
+    if signing_keys.is_empty() {
+        return Err(Report::new(AdvertisementError::Signature)
+            .attach("Advertisement does not have any signing keys"));
+    }
+    for key in signing_keys {
+        let is_signed = signatures
+            .iter()
+            .any(|signature| verify_signature(key, signature, &jws.payload).is_some());
+        if !is_signed {
+            return Err(Report::new(AdvertisementError::Signature)
+                .attach_key_value("Thumbprint", &key.thumbprint()));
+        }
+    }
+    if advertisement.get_exchange_key().is_none() {
+        return Err(Report::new(AdvertisementError::ExchangeKey));
+    }
+    Ok(advertisement)
+}
+
+/// Verify an ES512 signature of the payload.
+///
+/// Returns `None` if the signature isn't valid for the key.
+fn verify_signature(key: &Jwk, signature: &JwsSignature, payload: &str) -> Option<()> {
+    let header = URL_SAFE_NO_PAD.decode(&signature.protected).ok()?;
+    let header: JwsHeader = serde_json::from_slice(&header).ok()?;
+    if header.alg != SIGNATURE_ALGORITHM {
+        return None;
+    }
+    let verifying_key = VerifyingKey::from_affine(key.to_point().ok()?).ok()?;
+    let bytes = URL_SAFE_NO_PAD.decode(&signature.signature).ok()?;
+    let value = Signature::from_slice(&bytes).ok()?;
+    let input = format!("{}.{payload}", signature.protected);
+    verifying_key.verify(input.as_bytes(), &value).ok()
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum AdvertisementError {
+    #[error("Unable to fetch advertisement from Tang server")]
+    Request,
+    #[error("Unable to deserialize Tang advertisement")]
+    Deserialize,
+    #[error("Tang advertisement is not signed by its signing keys")]
+    Signature,
+    #[error("Tang advertisement does not have an exchange key")]
+    ExchangeKey,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn _fetch_advertisement() {
+        // Arrange
+        let server = TangTestServer::start();
+
+        // Act
+        let result = fetch_advertisement(&server.url);
+
+        // Assert
+        let advertisement = result.expect("Should be able to fetch advertisement");
+        let signing_keys = advertisement.get_signing_keys();
+        assert_eq!(signing_keys.len(), 1);
+        assert_eq!(
+            signing_keys.first().map(|key| key.thumbprint()),
+            Some(server.get_thumbprint())
+        );
+        assert!(advertisement.get_exchange_key().is_some());
+    }
+
+    #[test]
+    fn parse_advertisement_tampered() {
+        // Arrange
+        let server = TangTestServer::start();
+        let other = TangTestServer::start();
+        let signed: Value = serde_json::from_str(&server.get_advertisement())
+            .expect("Should be able to parse advertisement");
+        let mut tampered: Value = serde_json::from_str(&other.get_advertisement())
+            .expect("Should be able to parse advertisement");
+        let payload = signed.get("payload").expect("Should have payload").clone();
+        *tampered.get_mut("payload").expect("Should have payload") = payload;
+
+        // Act
+        let result = parse_advertisement(&tampered.to_string());
+
+        // Assert
+        let report = result.expect_err("Should reject signature of another server");
+        assert_eq!(report.current_context(), &AdvertisementError::Signature);
+    }
+}
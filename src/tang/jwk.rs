@@ -0,0 +1,153 @@
+use crate::prelude::*;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use p521::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use p521::{AffinePoint, EncodedPoint, FieldBytes};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Key type of elliptic curve keys.
+const EC_KEY_TYPE: &str = "EC";
+
+/// Curve of the keys advertised by Tang.
+const CURVE: &str = "P-521";
+
+/// Length in bytes of a P-521 coordinate.
+const COORDINATE_LENGTH: usize = 66;
+
+/// JSON Web Key of an elliptic curve public key.
+///
+/// - <https://datatracker.ietf.org/doc/html/rfc7517>
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Jwk {
+    /// Key type
+    pub kty: String,
+    /// Curve
+    pub crv: String,
+    /// X coordinate encoded as unpadded base64url
+    pub x: String,
+    /// Y coordinate encoded as unpadded base64url
+    pub y: String,
+    /// Algorithm the key is used with
+    ///
+    /// Examples: `ES512`, `ECMR`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// Operations the key is used for
+    ///
+    /// Examples: `verify`, `deriveKey`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_ops: Vec<String>,
+}
+
+impl Jwk {
+    /// Encode a P-521 point.
+    pub fn from_point(point: &AffinePoint) -> Result<Jwk, Report<JwkError>> {
+        let encoded = point.to_encoded_point(false);
+        let (Some(x), Some(y)) = (encoded.x(), encoded.y()) else {
+            return Err(Report::new(JwkError::Point).attach("Point is the identity"));
+        };
+        Ok(Jwk {
+            kty: EC_KEY_TYPE.to_owned(),
+            crv: CURVE.to_owned(),
+            x: URL_SAFE_NO_PAD.encode(x),
+            y: URL_SAFE_NO_PAD.encode(y),
+            alg: None,
+            key_ops: Vec::new(),
+        })
+    }
+
+    /// Decode the P-521 point of the key.
+    pub fn to_point(&self) -> Result<AffinePoint, Report<JwkError>> {
+        if self.kty != EC_KEY_TYPE || self.crv != CURVE {
+            return Err(Report::new(JwkError::Curve)
+                .attach_key_value("Key type", &self.kty)
+                .attach_key_value("Curve", &self.crv));
+        }
+        let x = decode_coordinate(&self.x)?;
+        let y = decode_coordinate(&self.y)?;
+        let encoded = EncodedPoint::from_affine_coordinates(&x, &y, false);
+        Option::from(AffinePoint::from_encoded_point(&encoded))
+            .ok_or_else(|| Report::new(JwkError::Point).attach("Point is not on the curve"))
+    }
+
+    /// Get the SHA-256 thumbprint of the key.
+    ///
+    /// - <https://datatracker.ietf.org/doc/html/rfc7638>
+    #[must_use]
+    pub fn thumbprint(&self) -> String {
+        let members = format!(
+            r#"{{"crv":"{}","kty":"{}","x":"{}","y":"{}"}}"#,
+            self.crv, self.kty, self.x, self.y
+        );
+        URL_SAFE_NO_PAD.encode(Sha256::digest(members.as_bytes()))
+    }
+
+    /// Check the key can be used for an operation.
+    #[must_use]
+    pub fn has_operation(&self, operation: &str) -> bool {
+        self.key_ops.iter().any(|op| op == operation)
+    }
+}
+
+fn decode_coordinate(value: &str) -> Result<FieldBytes, Report<JwkError>> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(value)
+        .change_context(JwkError::Encoding)?;
+    if bytes.len() != COORDINATE_LENGTH {
+        return Err(
+            Report::new(JwkError::Encoding).attach_key_value("Length", &bytes.len().to_string())
+        );
+    }
+    let mut coordinate = FieldBytes::default();
+    coordinate.copy_from_slice(&bytes);
+    Ok(coordinate)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum JwkError {
+    #[error("Only P-521 elliptic curve keys are supported")]
+    Curve,
+    #[error("Unable to decode key coordinate")]
+    Encoding,
+    #[error("Key is not a valid point")]
+    Point,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p521::ProjectivePoint;
+
+    #[test]
+    fn jwk_point_round_trip() {
+        // Arrange
+        let point = ProjectivePoint::GENERATOR.to_affine();
+
+        // Act
+        let jwk = Jwk::from_point(&point).expect("Should be able to encode point");
+        let decoded = jwk.to_point().expect("Should be able to decode point");
+
+        // Assert
+        assert_eq!(decoded, point);
+        assert_eq!(jwk.crv, "P-521");
+    }
+
+    #[test]
+    fn jwk_thumbprint() {
+        // Arrange
+        let first = Jwk::from_point(&ProjectivePoint::GENERATOR.to_affine())
+            .expect("Should be able to encode point");
+        let mut second = first.clone();
+        second.alg = Some("ECMR".to_owned());
+        second.key_ops = vec!["deriveKey".to_owned()];
+
+        // Act
+        let first = first.thumbprint();
+        let second = second.thumbprint();
+
+        // Assert
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 43);
+    }
+}
@@ -0,0 +1,15 @@
+mod fetch_advertisement;
+mod jwk;
+mod set_tang_command;
+mod tang_agent;
+mod tang_binding;
+#[cfg(test)]
+mod tang_test_server;
+
+pub use fetch_advertisement::*;
+pub use jwk::*;
+pub use set_tang_command::*;
+pub use tang_agent::*;
+pub use tang_binding::*;
+#[cfg(test)]
+pub use tang_test_server::*;
@@ -0,0 +1,71 @@
+use crate::prelude::*;
+
+pub fn set_tang_command(options: Options) -> Result<(), AnyReport> {
+    let counter = Mutex::new(0);
+    let total_steps = 4;
+
+    print_step_start(&counter, total_steps, "Checking if root");
+    is_root()?;
+    print_step_completed("Access granted");
+
+    print_step_start(&counter, total_steps, "Fetching Tang advertisement");
+    let url = options
+        .tang_url
+        .as_deref()
+        .ok_or_else(|| Report::new(TangError::Bind).attach("`tang_url` is not set"))?;
+    let advertisement = fetch_advertisement(url)?;
+    print_step_completed("Verified Tang advertisement");
+
+    print_step_start(&counter, total_steps, "Checking Tang signing key");
+    check_trusted(&options, &advertisement)?;
+    print_step_completed("Tang signing key is trusted");
+
+    print_step_start(&counter, total_steps, "Binding to Tang server");
+    let (binding, _) = TangBinding::bind(url, &advertisement)?;
+    let path = options.get_tang_binding_path();
+    write_tang_binding(&path, &binding)?;
+    print_step_completed(&format!(
+        "Saved Tang binding to {}, run `set-luks` to add the new key",
+        path.display()
+    ));
+
+    Ok(())
+}
+
+/// Check the advertisement is signed by the key of `tang_thumbprint`.
+///
+/// If `tang_thumbprint` is not set then the thumbprints are shown so they can be compared
+/// with `tang-show-keys` on the server, and the confirmed thumbprint is saved.
+fn check_trusted(
+    options: &Options,
+    advertisement: &Advertisement,
+) -> Result<(), Report<TangError>> {
+    let thumbprints: Vec<String> = advertisement
+        .get_signing_keys()
+        .iter()
+        .map(|key| key.thumbprint())
+        .collect();
+    if let Some(thumbprint) = &options.tang_thumbprint {
+        if thumbprints.contains(thumbprint) {
+            return Ok(());
+        }
+        return Err(Report::new(TangError::Untrusted)
+            .attach_key_value("Expected", thumbprint)
+            .attach_key_value("Advertised", &thumbprints.join(", ")));
+    }
+    for thumbprint in &thumbprints {
+        info!("Tang signing key SHA-256 thumbprint: {thumbprint}");
+    }
+    let is_trusted =
+        prompt_confirm("Do you trust these keys?").change_context(TangError::Untrusted)?;
+    if !is_trusted {
+        return Err(Report::new(TangError::Untrusted));
+    }
+    let thumbprint = thumbprints
+        .first()
+        .ok_or_else(|| Report::new(TangError::Untrusted))?;
+    options
+        .save_option("tang_thumbprint", thumbprint)
+        .change_context(TangError::Untrusted)
+        .attach(format!("Add `tang_thumbprint: {thumbprint}` manually"))
+}
@@ -0,0 +1,14 @@
+use std::time::Duration;
+use ureq::Agent;
+
+/// Maximum time to wait for a response from the Tang server.
+const TANG_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Get the HTTP agent used for requests to a Tang server.
+#[must_use]
+pub fn get_tang_agent() -> Agent {
+    Agent::config_builder()
+        .timeout_global(Some(TANG_TIMEOUT))
+        .build()
+        .new_agent()
+}
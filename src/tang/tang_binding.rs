@@ -0,0 +1,253 @@
+use crate::prelude::*;
+use hkdf::Hkdf;
+use p521::elliptic_curve::rand_core::OsRng;
+use p521::elliptic_curve::sec1::ToEncodedPoint;
+use p521::{AffinePoint, NonZeroScalar, ProjectivePoint};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::read_to_string;
+
+/// Version of the binding file format.
+const BINDING_VERSION: u8 = 1;
+
+/// Salt of the HKDF-SHA256 derivation of the key component.
+const COMPONENT_SALT: &[u8] = b"mount-luks";
+
+/// Context of the HKDF-SHA256 derivation of the key component.
+const COMPONENT_INFO: &[u8] = b"mount-luks/tang/v1";
+
+/// Length in bytes of the derived key component.
+const COMPONENT_LENGTH: usize = 32;
+
+/// Binding of a volume to the exchange key of a Tang server.
+///
+/// Only public keys are stored. The key component can only be recovered with the help of
+/// the Tang server, which never learns the component itself.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TangBinding {
+    /// Version of the file format
+    pub version: u8,
+    /// URL of the Tang server
+    pub url: String,
+    /// Thumbprint of the server exchange key
+    pub kid: String,
+    /// Exchange key of the server
+    pub server_key: Jwk,
+    /// Public key of the client key pair, the private key is discarded after binding
+    pub client_key: Jwk,
+}
+
+impl TangBinding {
+    /// Bind to the exchange key of a verified advertisement.
+    ///
+    /// A client key pair is generated and the key component is derived from its shared
+    /// point with the server exchange key. The client private key is then discarded.
+    pub fn bind(
+        url: &str,
+        advertisement: &Advertisement,
+    ) -> Result<(TangBinding, Secret), Report<TangError>> {
+        let server_key = advertisement
+            .get_exchange_key()
+            .ok_or_else(|| Report::new(TangError::Bind))?;
+        let server_point = server_key.to_point().change_context(TangError::Bind)?;
+        let client_secret = NonZeroScalar::random(&mut OsRng);
+        let client_point = (ProjectivePoint::GENERATOR * *client_secret).to_affine();
+        let shared = (ProjectivePoint::from(server_point) * *client_secret).to_affine();
+        let binding = TangBinding {
+            version: BINDING_VERSION,
+            url: url.to_owned(),
+            kid: server_key.thumbprint(),
+            server_key: server_key.clone(),
+            client_key: Jwk::from_point(&client_point).change_context(TangError::Bind)?,
+        };
+        Ok((binding, derive_component(&shared)?))
+    }
+
+    /// Recover the key component with a McCallum-Relyea exchange.
+    ///
+    /// The client key is blinded with an ephemeral key so neither the Tang server nor anyone
+    /// watching the network learns the key component.
+    ///
+    /// - <https://github.com/latchset/tang#recovery>
+    pub fn recover(&self) -> Result<Secret, Report<TangError>> {
+        let server_point = self
+            .server_key
+            .to_point()
+            .change_context(TangError::Deserialize)?;
+        let client_point = self
+            .client_key
+            .to_point()
+            .change_context(TangError::Deserialize)?;
+        let ephemeral = NonZeroScalar::random(&mut OsRng);
+        let blinded = (ProjectivePoint::from(client_point)
+            + ProjectivePoint::GENERATOR * *ephemeral)
+            .to_affine();
+        let mut request = Jwk::from_point(&blinded).change_context(TangError::Request)?;
+        request.alg = Some(EXCHANGE_ALGORITHM.to_owned());
+        request.key_ops = vec![DERIVE_OPERATION.to_owned()];
+        let body = serde_json::to_string(&request).change_context(TangError::Request)?;
+        let url = format!("{}/rec/{}", self.url.trim_end_matches('/'), self.kid);
+        let response = get_tang_agent()
+            .post(&url)
+            .header("Content-Type", "application/jwk+json")
+            .send(body)
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .change_context(TangError::Request)
+            .attach_key_value("URL", &url)?;
+        let response: Jwk = serde_json::from_str(&response)
+            .change_context(TangError::Response)
+            .attach_key_value("URL", &url)?;
+        let response = response
+            .to_point()
+            .change_context(TangError::Response)
+            .attach_key_value("URL", &url)?;
+        let shared = (ProjectivePoint::from(response)
+            - ProjectivePoint::from(server_point) * *ephemeral)
+            .to_affine();
+        derive_component(&shared)
+    }
+}
+
+/// Read the Tang binding of a volume.
+pub fn read_tang_binding(path: &Path) -> Result<TangBinding, Report<TangError>> {
+    let content = read_to_string(path)
+        .change_context(TangError::Read)
+        .attach_path(path)?;
+    let binding: TangBinding = serde_yaml::from_str(&content)
+        .change_context(TangError::Deserialize)
+        .attach_path(path)?;
+    if binding.version != BINDING_VERSION {
+        return Err(Report::new(TangError::Version)
+            .attach_key_value("Version", &binding.version.to_string())
+            .attach_path(path));
+    }
+    Ok(binding)
+}
+
+/// Write the Tang binding of a volume.
+pub fn write_tang_binding(path: &Path, binding: &TangBinding) -> Result<(), Report<TangError>> {
+    let content = serde_yaml::to_string(binding).change_context(TangError::Write)?;
+    write_private_file(path, content.as_bytes())
+        .change_context(TangError::Write)
+        .attach_path(path)
+}
+
+/// Derive the key component from the x coordinate of the shared point.
+fn derive_component(shared: &AffinePoint) -> Result<Secret, Report<TangError>> {
+    let encoded = shared.to_encoded_point(false);
+    let x = encoded
+        .x()
+        .ok_or_else(|| Report::new(TangError::Response).attach("Shared point is the identity"))?;
+    let hkdf = Hkdf::<Sha256>::new(Some(COMPONENT_SALT), x);
+    let mut output = Secret::zeroed(COMPONENT_LENGTH);
+    hkdf.expand(COMPONENT_INFO, output.expose_mut())
+        .expect("Component length should be valid for HKDF-SHA256");
+    let mut component = Secret::zeroed(COMPONENT_LENGTH * 2);
+    hex::encode_to_slice(output.expose(), component.expose_mut())
+        .expect("Hex buffer should be twice the component length");
+    Ok(component)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum TangError {
+    #[error("Tang signing key is not trusted")]
+    Untrusted,
+    #[error("Unable to bind to Tang exchange key")]
+    Bind,
+    #[error("Unable to read Tang binding file, run `set-tang` first")]
+    Read,
+    #[error("Unable to deserialize Tang binding file")]
+    Deserialize,
+    #[error("Unsupported Tang binding file version")]
+    Version,
+    #[error("Unable to write Tang binding file")]
+    Write,
+    #[error("Tang URL has changed, run `set-tang` again")]
+    Url,
+    #[error("Unable to recover key from Tang server")]
+    Request,
+    #[error("Invalid response from Tang server")]
+    Response,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn bind_test_server(server: &TangTestServer) -> (TangBinding, Secret) {
+        let advertisement =
+            fetch_advertisement(&server.url).expect("Should be able to fetch advertisement");
+        TangBinding::bind(&server.url, &advertisement).expect("Should be able to bind")
+    }
+
+    #[test]
+    fn tang_binding_recover() {
+        // Arrange
+        let server = TangTestServer::start();
+        let (binding, component) = bind_test_server(&server);
+
+        // Act
+        let first = binding.recover().expect("Should be able to recover");
+        let second = binding.recover().expect("Should be able to recover again");
+
+        // Assert
+        assert_eq!(first.expose(), component.expose());
+        assert_eq!(second.expose(), component.expose());
+        assert_eq!(component.len(), COMPONENT_LENGTH * 2);
+    }
+
+    #[test]
+    fn tang_binding_recover_other_server() {
+        // Arrange
+        let server = TangTestServer::start();
+        let other = TangTestServer::start();
+        let (mut binding, _) = bind_test_server(&server);
+        binding.url.clone_from(&other.url);
+
+        // Act
+        let result = binding.recover();
+
+        // Assert
+        let report = result.expect_err("Should not know the exchange key");
+        assert_eq!(report.current_context(), &TangError::Request);
+    }
+
+    #[test]
+    fn tang_binding_recover_unreachable() {
+        // Arrange
+        let server = TangTestServer::start();
+        let (mut binding, _) = bind_test_server(&server);
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Should be able to bind listener");
+        let address = listener
+            .local_addr()
+            .expect("Should be able to get local address");
+        drop(listener);
+        binding.url = format!("http://{address}");
+
+        // Act
+        let result = binding.recover();
+
+        // Assert
+        let report = result.expect_err("Should not reach the server");
+        assert_eq!(report.current_context(), &TangError::Request);
+    }
+
+    #[test]
+    fn tang_binding_round_trip() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("e.tang");
+        let server = TangTestServer::start();
+        let (binding, _) = bind_test_server(&server);
+
+        // Act
+        write_tang_binding(&path, &binding).expect("Should be able to write binding");
+        let result = read_tang_binding(&path);
+
+        // Assert
+        assert_eq!(result.expect("Should be able to read binding"), binding);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::prelude::*;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use p521::ecdsa::signature::Signer;
+use p521::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p521::elliptic_curve::rand_core::OsRng;
+use p521::{NonZeroScalar, ProjectivePoint};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread::spawn;
+
+/// In-process stand-in for a Tang server.
+///
+/// Serves `GET /adv` and `POST /rec/{kid}` on a random local port so the McCallum-Relyea
+/// exchange can be tested without a real Tang server. The server runs until the test process
+/// exits.
+pub struct TangTestServer {
+    pub url: String,
+    keys: Arc<TangTestKeys>,
+}
+
+struct TangTestKeys {
+    signing_key: SigningKey,
+    exchange_key: NonZeroScalar,
+}
+
+impl TangTestServer {
+    /// Generate new keys and start serving them.
+    #[must_use]
+    pub fn start() -> TangTestServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Should be able to bind listener");
+        let address = listener
+            .local_addr()
+            .expect("Should be able to get local address");
+        let keys = Arc::new(TangTestKeys {
+            signing_key: SigningKey::random(&mut OsRng),
+            exchange_key: NonZeroScalar::random(&mut OsRng),
+        });
+        let server_keys = keys.clone();
+        spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                server_keys.handle(stream);
+            }
+        });
+        TangTestServer {
+            url: format!("http://{address}"),
+            keys,
+        }
+    }
+
+    /// Get the signed advertisement.
+    #[must_use]
+    pub fn get_advertisement(&self) -> String {
+        self.keys.get_advertisement()
+    }
+
+    /// Get the thumbprint of the signing key.
+    #[must_use]
+    pub fn get_thumbprint(&self) -> String {
+        self.keys.get_signing_jwk().thumbprint()
+    }
+}
+
+impl TangTestKeys {
+    fn handle(&self, mut stream: TcpStream) {
+        let Some((method, path, body)) = read_request(&stream) else {
+            return;
+        };
+        let response = match (method.as_str(), path.split_once("/rec/")) {
+            ("GET", _) if path == "/adv" => Some(self.get_advertisement()),
+            ("POST", Some(("", kid))) => self.recover(kid, &body),
+            _ => None,
+        };
+        let response = match response {
+            Some(body) => format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            ),
+            None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_owned(),
+        };
+        let _ = stream.write_all(response.as_bytes());
+    }
+
+    fn get_signing_jwk(&self) -> Jwk {
+        let point = *VerifyingKey::from(&self.signing_key).as_affine();
+        let mut jwk = Jwk::from_point(&point).expect("Should be able to encode signing key");
+        jwk.alg = Some("ES512".to_owned());
+        jwk.key_ops = vec!["sign".to_owned(), "verify".to_owned()];
+        jwk
+    }
+
+    fn get_exchange_jwk(&self) -> Jwk {
+        let point = (ProjectivePoint::GENERATOR * *self.exchange_key).to_affine();
+        let mut jwk = Jwk::from_point(&point).expect("Should be able to encode exchange key");
+        jwk.alg = Some(EXCHANGE_ALGORITHM.to_owned());
+        jwk.key_ops = vec![DERIVE_OPERATION.to_owned()];
+        jwk
+    }
+
+    fn get_advertisement(&self) -> String {
+        let keys = serde_json::json!({
+            "keys": [self.get_signing_jwk(), self.get_exchange_jwk()],
+        });
+        let payload = URL_SAFE_NO_PAD.encode(keys.to_string());
+        let protected = URL_SAFE_NO_PAD.encode(r#"{"alg":"ES512","cty":"jwk-set+json"}"#);
+        let signature: Signature = self
+            .signing_key
+            .sign(format!("{protected}.{payload}").as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(signature.to_bytes());
+        serde_json::json!({
+            "payload": payload,
+            "signatures": [{ "protected": protected, "signature": signature }],
+        })
+        .to_string()
+    }
+
+    /// Multiply the blinded client key by the exchange key.
+    fn recover(&self, kid: &str, body: &str) -> Option<String> {
+        if kid != self.get_exchange_jwk().thumbprint() {
+            return None;
+        }
+        let request: Jwk = serde_json::from_str(body).ok()?;
+        let point = ProjectivePoint::from(request.to_point().ok()?);
+        let response = (point * *self.exchange_key).to_affine();
+        let mut jwk = Jwk::from_point(&response).ok()?;
+        jwk.alg = Some(EXCHANGE_ALGORITHM.to_owned());
+        jwk.key_ops = vec![DERIVE_OPERATION.to_owned()];
+        serde_json::to_string(&jwk).ok()
+    }
+}
+
+/// Read the method, path and body of an HTTP/1.1 request.
+fn read_request(stream: &TcpStream) -> Option<(String, String, String)> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_owned();
+    let path = parts.next()?.to_owned();
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().ok()?;
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some((method, path, String::from_utf8(body).ok()?))
+}
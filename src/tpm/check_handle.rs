@@ -2,8 +2,8 @@ use crate::prelude::*;
 
 /// Check if the TPM handle is already in use.
 pub fn check_handle(options: &Options) -> Result<(), Report<CheckHandleError>> {
-    let target_handle = options.tpm_handle.unwrap_or_default();
-    let handles = get_handles()?;
+    let target_handle = options.get_tpm_handle().unwrap_or_default();
+    let handles = tpm_backend().get_handles()?;
     if handles.contains(&target_handle) {
         let report = Report::new(CheckHandleError::HandleInUse)
             .attach_key_value("Handle", &target_handle.to_string());
@@ -76,7 +76,7 @@ mod tests {
 
         // Arrange
         let options = Options {
-            tpm_handle: Some(EXAMPLE_HANDLE.to_owned()),
+            tpm_handle: Some(EXAMPLE_HANDLE.to_owned().into()),
             ..Options::default()
         };
 
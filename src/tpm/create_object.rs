@@ -1,17 +1,49 @@
 use crate::prelude::*;
+use nix::fcntl::{FcntlArg, FdFlag, fcntl};
 use rpassword::prompt_password;
+use std::io::{PipeReader, Write, pipe};
+use std::os::fd::AsRawFd;
 use std::process::Stdio;
 
-/// Create a child object.
+/// Prompt for the key to seal in the TPM.
 ///
-/// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_create.1/>
-pub fn create_object() -> Result<(), Report<CreateObjectError>> {
-    let key = prompt_password("Enter the key:").change_context(CreateObjectError::Prompt)?;
-    create_object_from_input(&key)
+/// If the `tpm_pin` option is set then a PIN is also prompted for which will be required to
+/// unseal the object.
+pub fn prompt_sealing_input(
+    options: &Options,
+) -> Result<(Secret, Option<Secret>), Report<CreateObjectError>> {
+    let key = prompt_password("Enter the key:")
+        .map(Secret::from)
+        .change_context(CreateObjectError::Prompt)?;
+    let pin = if options.tpm_pin == Some(true) {
+        Some(prompt_new_pin()?)
+    } else {
+        None
+    };
+    Ok((key, pin))
 }
 
-pub fn create_object_from_input(key: &str) -> Result<(), Report<CreateObjectError>> {
-    Command::new("tpm2_create")
+/// Create a child object sealing `key`.
+///
+/// The key is written to stdin and the PIN is read from an inherited pipe so neither is
+/// visible in the process list.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_create.1/>
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/authorizations/>
+pub fn create_object_from_input(
+    key: &Secret,
+    pin: Option<&Secret>,
+) -> Result<(), Report<CreateObjectError>> {
+    let pin = pin.map(write_pin_to_pipe).transpose()?;
+    let mut command = Command::new("tpm2_create");
+    if let Some(reader) = &pin {
+        command
+            .arg("--key-auth")
+            .arg(format!("file:/dev/fd/{}", reader.as_raw_fd()))
+            .arg("--attributes")
+            .arg(PIN_OBJECT_ATTRIBUTES);
+    }
+    command
         .arg("--parent-context")
         .arg(TPM_PRIMARY_CONTEXT_PATH.display().to_string())
         .arg("--hash-algorithm")
@@ -29,16 +61,47 @@ pub fn create_object_from_input(key: &str) -> Result<(), Report<CreateObjectErro
         .stderr(Stdio::piped())
         .spawn()
         .expect("should be able to spawn `tpm2_create`")
-        .write_to_stdin(key)
+        .write_to_stdin(key.expose())
         .wait_with_output()
         .expect("should be able to wait for `tpm2_create`")
         .ok_or(CreateObjectError::Failed)
 }
 
+/// Write the PIN to a pipe whose read end is inherited by child processes.
+fn write_pin_to_pipe(pin: &Secret) -> Result<PipeReader, Report<CreateObjectError>> {
+    let (reader, mut writer) = pipe().change_context(CreateObjectError::Failed)?;
+    writer
+        .write_all(pin.expose())
+        .change_context(CreateObjectError::Failed)?;
+    drop(writer);
+    fcntl(&reader, FcntlArg::F_SETFD(FdFlag::empty())).change_context(CreateObjectError::Failed)?;
+    Ok(reader)
+}
+
+fn prompt_new_pin() -> Result<Secret, Report<CreateObjectError>> {
+    let pin = prompt_password("Enter the TPM PIN:")
+        .map(Secret::from)
+        .change_context(CreateObjectError::Prompt)?;
+    let confirm = prompt_password("Confirm the TPM PIN:")
+        .map(Secret::from)
+        .change_context(CreateObjectError::Prompt)?;
+    if pin.expose() != confirm.expose() {
+        bail!(CreateObjectError::PinMismatch);
+    }
+    if pin.is_empty() {
+        bail!(CreateObjectError::PinRequired);
+    }
+    Ok(pin)
+}
+
 #[derive(Clone, Copy, Debug, Error, PartialEq)]
 pub enum CreateObjectError {
     #[error("Unable to read key from prompt")]
     Prompt,
+    #[error("TPM PIN does not match")]
+    PinMismatch,
+    #[error("TPM PIN must not be empty")]
+    PinRequired,
     #[error("Unable to create object")]
     Failed,
 }
@@ -51,12 +114,12 @@ mod tests {
     fn _create_object_from_input() {
         assert!(is_root().is_ok(), "Root is required to run this test");
         // Arrange
-        let input = "Hello, world!";
-        create_policy().expect("Should be able to create policy");
+        let input = Secret::from("Hello, world!".to_owned());
+        create_policy(&PcrPolicy::default(), false).expect("Should be able to create policy");
         create_primary().expect("Should be able to create primary");
 
         // Act
-        let result = create_object_from_input(input);
+        let result = create_object_from_input(&input, None);
 
         // Preview
         if let Err(report) = &result {
@@ -2,17 +2,34 @@ use crate::prelude::*;
 
 /// Create a policy that requires the TPM to have a certain PCR value.
 ///
+/// If `pin` is true the policy also requires the auth value of the object.
+///
 /// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_createpolicy.1/>
-pub fn create_policy() -> Result<(), Report<CreatePolicyError>> {
+pub fn create_policy(policy: &PcrPolicy, pin: bool) -> Result<(), Report<CreatePolicyError>> {
+    if pin {
+        return create_pin_policy(policy);
+    }
     Command::new("tpm2_createpolicy")
         .arg("--policy-pcr")
         .arg("--pcr-list")
-        .arg(POLICY.to_owned())
+        .arg(policy.to_string())
         .arg("--policy")
         .arg(TPM_POLICY_PATH.display().to_string())
         .output()
         .expect("should be able to execute `tpm2_createpolicy`")
         .ok_or(CreatePolicyError)
+        .attach_key_value("Policy", &policy.to_string())
+}
+
+/// Create a policy that requires both the PCR value and the auth value of the object.
+///
+/// The policy digest is calculated in a trial session which is always flushed.
+fn create_pin_policy(policy: &PcrPolicy) -> Result<(), Report<CreatePolicyError>> {
+    start_session(false).change_context(CreatePolicyError)?;
+    let result =
+        apply_pin_policy(policy, Some(TPM_POLICY_PATH.as_path())).change_context(CreatePolicyError);
+    flush_session().change_context(CreatePolicyError)?;
+    result
 }
 
 #[derive(Clone, Copy, Debug, Error, PartialEq)]
@@ -29,7 +46,7 @@ mod tests {
         assert!(is_root().is_ok(), "Root is required to run this test");
         // Arrange
         // Act
-        let result = create_policy();
+        let result = create_policy(&PcrPolicy::default(), false);
 
         // Preview
         if let Err(report) = &result {
@@ -32,9 +32,10 @@ mod tests {
         assert!(is_root().is_ok(), "Root is required to run this test");
         // Arrange
         let input = "Hello, world!";
-        create_policy().expect("Should be able to create policy");
+        create_policy(&PcrPolicy::default(), false).expect("Should be able to create policy");
         create_primary().expect("Should be able to create primary");
-        create_object_from_input(input).expect("Should be able to create object");
+        create_object_from_input(&Secret::from(input.to_owned()), None)
+            .expect("Should be able to create object");
 
         // Act
         let result = load_object();
@@ -46,7 +47,8 @@ mod tests {
 
         // Assert
         assert!(result.is_ok());
-        let value = unseal_object_from_context_path().expect("Should be able to unseal object");
-        assert_eq!(value, input);
+        let value = unseal_object_from_context_path(&PcrPolicy::default(), None)
+            .expect("Should be able to unseal object");
+        assert_eq!(value.expose(), input.as_bytes());
     }
 }
@@ -4,11 +4,21 @@ mod create_policy;
 mod create_primary_key;
 mod evict_object;
 mod load_object;
+#[cfg(feature = "native-tpm")]
+mod native_tpm_backend;
 mod next_handle;
+mod pcr_policy;
 mod persist_object;
 mod persistent_handle;
+mod policy_session;
+mod rotate_tpm_command;
 mod set_tpm_command;
+mod temp_files;
+mod tpm_backend;
 mod tpm_constants;
+mod tpm_evict_command;
+mod tpm_handle;
+mod tpm_list_command;
 mod unseal_object;
 
 pub use check_handle::*;
@@ -17,9 +27,19 @@ pub use create_policy::*;
 pub use create_primary_key::*;
 pub use evict_object::*;
 pub use load_object::*;
+#[cfg(feature = "native-tpm")]
+pub use native_tpm_backend::*;
 pub use next_handle::*;
+pub use pcr_policy::*;
 pub use persist_object::*;
 pub use persistent_handle::*;
+pub use policy_session::*;
+pub use rotate_tpm_command::*;
 pub use set_tpm_command::*;
+pub use temp_files::*;
+pub use tpm_backend::*;
 pub use tpm_constants::*;
+pub use tpm_evict_command::*;
+pub use tpm_handle::*;
+pub use tpm_list_command::*;
 pub use unseal_object::*;
@@ -0,0 +1,366 @@
+use crate::prelude::*;
+use tss_esapi::Context;
+use tss_esapi::attributes::ObjectAttributesBuilder;
+use tss_esapi::constants::CapabilityType;
+use tss_esapi::constants::SessionType;
+use tss_esapi::constants::response_code::Tss2ResponseCodeKind;
+use tss_esapi::constants::tss::TPM2_PERSISTENT_FIRST;
+use tss_esapi::handles::{
+    KeyHandle, ObjectHandle, PersistentTpmHandle, SessionHandle, TpmHandle as NativeTpmHandle,
+};
+use tss_esapi::interface_types::algorithm::{HashingAlgorithm, PublicAlgorithm};
+use tss_esapi::interface_types::dynamic_handles::Persistent;
+use tss_esapi::interface_types::key_bits::RsaKeyBits;
+use tss_esapi::interface_types::resource_handles::{Hierarchy, Provision};
+use tss_esapi::interface_types::session_handles::{AuthSession, PolicySession};
+use tss_esapi::structures::{
+    Auth, CapabilityData, Digest, KeyedHashScheme, PcrSelectionList, PcrSlot, Public,
+    PublicBuilder, PublicKeyedHashParameters, RsaExponent, SensitiveData, SymmetricDefinition,
+    SymmetricDefinitionObject,
+};
+use tss_esapi::tcti_ldr::{DeviceConfig, TctiNameConf};
+use tss_esapi::utils::create_restricted_decryption_rsa_public;
+
+/// TPM device used if no TCTI is set in the environment.
+const DEFAULT_DEVICE: &str = "/dev/tpmrm0";
+
+/// TPM backend that uses the TSS2 ESAPI.
+///
+/// Each operation opens its own context so no TPM state is kept between calls and no
+/// intermediate objects are written to disk.
+///
+/// The TCTI is read from the `TPM2TOOLS_TCTI`, `TCTI` or `TEST_TCTI` environment variables,
+/// otherwise the kernel resource manager is used.
+///
+/// - <https://docs.rs/tss-esapi/>
+pub struct NativeTpmBackend;
+
+impl TpmBackend for NativeTpmBackend {
+    fn get_handles(&self) -> Result<Vec<PersistentHandle>, Report<CheckHandleError>> {
+        let mut context = create_context().change_context(CheckHandleError)?;
+        let mut handles = Vec::new();
+        let mut property = TPM2_PERSISTENT_FIRST;
+        loop {
+            let (data, more) = context
+                .get_capability(CapabilityType::Handles, property, u32::from(u16::MAX))
+                .change_context(CheckHandleError)?;
+            let CapabilityData::Handles(list) = data else {
+                break;
+            };
+            let mut last = None;
+            for handle in list.iter() {
+                let value = u32::from(*handle);
+                last = Some(value);
+                if let Ok(handle) = PersistentHandle::new(value) {
+                    handles.push(handle);
+                }
+            }
+            match last {
+                Some(value) if more => property = value + 1,
+                _ => break,
+            }
+        }
+        Ok(handles)
+    }
+
+    fn seal(
+        &self,
+        handle: PersistentHandle,
+        policy: &PcrPolicy,
+        key: &Secret,
+        pin: Option<&Secret>,
+    ) -> Result<(), Report<SealError>> {
+        let mut context = create_context().change_context(SealError)?;
+        let digest = get_policy_digest(&mut context, policy, pin.is_some())?;
+        let primary = context
+            .execute_with_nullauth_session(|context| {
+                context.create_primary(
+                    Hierarchy::Owner,
+                    get_primary_public()?,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+            })
+            .change_context(SealError)
+            .attach("Unable to create primary key")?
+            .key_handle;
+        let result = create_sealed_object(&mut context, primary, digest, key, pin, handle);
+        context
+            .flush_context(primary.into())
+            .change_context(SealError)?;
+        result
+    }
+
+    fn unseal(
+        &self,
+        handle: PersistentHandle,
+        policy: &PcrPolicy,
+        pin: Option<&Secret>,
+    ) -> Result<Secret, Report<UnsealError>> {
+        let mut context = create_context()
+            .change_context(UnsealError)
+            .attach_key_value("Policy", &policy.to_string())?;
+        let object = get_persistent_object(&mut context, handle).change_context(UnsealError)?;
+        if let Some(pin) = pin {
+            let auth = Auth::try_from(pin.expose().to_vec()).change_context(UnsealError)?;
+            context
+                .tr_set_auth(object, auth)
+                .change_context(UnsealError)?;
+        }
+        let session =
+            start_policy_session(&mut context, SessionType::Policy).change_context(UnsealError)?;
+        apply_policy(&mut context, session, policy, pin.is_some())
+            .change_context(UnsealError)
+            .attach_key_value("Policy", &policy.to_string())?;
+        let data = context
+            .execute_with_session(Some(AuthSession::from(session)), |context| {
+                context.unseal(object)
+            })
+            .map_err(to_report)
+            .change_context(UnsealError)
+            .attach_key_value("Policy", &policy.to_string())?;
+        Ok(Secret::new(data.value().to_vec()).trim())
+    }
+
+    fn evict(&self, handle: PersistentHandle) -> Result<(), Report<EvictObjectError>> {
+        let mut context = create_context().change_context(EvictObjectError)?;
+        let object = get_persistent_object(&mut context, handle)
+            .change_context(EvictObjectError)
+            .attach_key_value("Handle", &handle.to_string())?;
+        let persistent = get_persistent(handle).change_context(EvictObjectError)?;
+        context
+            .execute_with_nullauth_session(|context| {
+                context.evict_control(Provision::Owner, object, persistent)
+            })
+            .map_err(to_report)
+            .change_context(EvictObjectError)
+            .attach_key_value("Handle", &handle.to_string())?;
+        Ok(())
+    }
+}
+
+fn create_context() -> Result<Context, Report<NativeTpmError>> {
+    let tcti = match TctiNameConf::from_environment_variable() {
+        Ok(tcti) => tcti,
+        Err(_) => {
+            let device =
+                DeviceConfig::from_str(DEFAULT_DEVICE).change_context(NativeTpmError::Context)?;
+            TctiNameConf::Device(device)
+        }
+    };
+    Context::new(tcti).change_context(NativeTpmError::Context)
+}
+
+/// Compute the policy digest with a trial session.
+fn get_policy_digest(
+    context: &mut Context,
+    policy: &PcrPolicy,
+    pin: bool,
+) -> Result<Digest, Report<SealError>> {
+    let session = start_policy_session(context, SessionType::Trial).change_context(SealError)?;
+    let result = apply_policy(context, session, policy, pin)
+        .and_then(|()| {
+            context
+                .policy_get_digest(session)
+                .change_context(NativeTpmError::Policy)
+        })
+        .change_context(SealError)
+        .attach_key_value("Policy", &policy.to_string());
+    context
+        .flush_context(SessionHandle::from(AuthSession::from(session)).into())
+        .change_context(SealError)?;
+    result
+}
+
+fn start_policy_session(
+    context: &mut Context,
+    session_type: SessionType,
+) -> Result<PolicySession, Report<NativeTpmError>> {
+    let session = context
+        .start_auth_session(
+            None,
+            None,
+            None,
+            session_type,
+            SymmetricDefinition::AES_128_CFB,
+            HashingAlgorithm::Sha256,
+        )
+        .change_context(NativeTpmError::Session)?
+        .ok_or(NativeTpmError::Session)?;
+    PolicySession::try_from(session).change_context(NativeTpmError::Session)
+}
+
+/// Restrict the session to the PCR policy and, if required, the object's auth value.
+fn apply_policy(
+    context: &mut Context,
+    session: PolicySession,
+    policy: &PcrPolicy,
+    pin: bool,
+) -> Result<(), Report<NativeTpmError>> {
+    let mut slots = Vec::new();
+    for pcr in policy.pcrs() {
+        let slot = PcrSlot::try_from(1_u32 << pcr).change_context(NativeTpmError::Policy)?;
+        slots.push(slot);
+    }
+    let selection = PcrSelectionList::builder()
+        .with_selection(get_hashing_algorithm(policy.bank()), &slots)
+        .build()
+        .change_context(NativeTpmError::Policy)?;
+    context
+        .policy_pcr(session, Digest::default(), selection)
+        .map_err(to_report)
+        .change_context(NativeTpmError::Policy)?;
+    if pin {
+        context
+            .policy_auth_value(session)
+            .map_err(to_report)
+            .change_context(NativeTpmError::Policy)?;
+    }
+    Ok(())
+}
+
+fn get_hashing_algorithm(bank: PcrBank) -> HashingAlgorithm {
+    match bank {
+        PcrBank::Sha1 => HashingAlgorithm::Sha1,
+        PcrBank::Sha256 => HashingAlgorithm::Sha256,
+        PcrBank::Sha384 => HashingAlgorithm::Sha384,
+        PcrBank::Sha512 => HashingAlgorithm::Sha512,
+    }
+}
+
+fn get_primary_public() -> Result<Public, tss_esapi::Error> {
+    create_restricted_decryption_rsa_public(
+        SymmetricDefinitionObject::AES_128_CFB,
+        RsaKeyBits::Rsa2048,
+        RsaExponent::default(),
+    )
+}
+
+/// Create, load and persist a sealed data object under `primary`.
+fn create_sealed_object(
+    context: &mut Context,
+    primary: KeyHandle,
+    digest: Digest,
+    key: &Secret,
+    pin: Option<&Secret>,
+    handle: PersistentHandle,
+) -> Result<(), Report<SealError>> {
+    let attributes = ObjectAttributesBuilder::new()
+        .with_fixed_tpm(true)
+        .with_fixed_parent(true)
+        .with_admin_with_policy(pin.is_some())
+        .build()
+        .change_context(SealError)?;
+    let public = PublicBuilder::new()
+        .with_public_algorithm(PublicAlgorithm::KeyedHash)
+        .with_name_hashing_algorithm(HashingAlgorithm::Sha256)
+        .with_object_attributes(attributes)
+        .with_auth_policy(digest)
+        .with_keyed_hash_parameters(PublicKeyedHashParameters::new(KeyedHashScheme::Null))
+        .with_keyed_hash_unique_identifier(Digest::default())
+        .build()
+        .change_context(SealError)?;
+    let sensitive = SensitiveData::try_from(key.expose().to_vec()).change_context(SealError)?;
+    let auth = match pin {
+        Some(pin) => Some(Auth::try_from(pin.expose().to_vec()).change_context(SealError)?),
+        None => None,
+    };
+    let created = context
+        .execute_with_nullauth_session(|context| {
+            context.create(primary, public, auth, Some(sensitive), None, None)
+        })
+        .map_err(to_report)
+        .change_context(SealError)
+        .attach("Unable to create object")?;
+    let loaded = context
+        .execute_with_nullauth_session(|context| {
+            context.load(primary, created.out_private, created.out_public)
+        })
+        .change_context(SealError)
+        .attach("Unable to load object")?;
+    let persistent = get_persistent(handle).change_context(SealError)?;
+    let result = context
+        .execute_with_nullauth_session(|context| {
+            context.evict_control(Provision::Owner, loaded.into(), persistent)
+        })
+        .map_err(to_report)
+        .change_context(SealError)
+        .attach("Unable to persist object")
+        .attach_key_value("Handle", &handle.to_string());
+    context
+        .flush_context(loaded.into())
+        .change_context(SealError)?;
+    result.map(|_| ())
+}
+
+fn get_persistent(handle: PersistentHandle) -> Result<Persistent, Report<NativeTpmError>> {
+    let persistent =
+        PersistentTpmHandle::new(handle.as_u32()).change_context(NativeTpmError::Handle)?;
+    Ok(Persistent::Persistent(persistent))
+}
+
+fn get_persistent_object(
+    context: &mut Context,
+    handle: PersistentHandle,
+) -> Result<ObjectHandle, Report<NativeTpmError>> {
+    let persistent =
+        PersistentTpmHandle::new(handle.as_u32()).change_context(NativeTpmError::Handle)?;
+    context
+        .tr_from_tpm_public(NativeTpmHandle::Persistent(persistent))
+        .map_err(to_report)
+        .change_context(NativeTpmError::Handle)
+        .attach_key_value("Handle", &handle.to_string())
+}
+
+/// Keep the TSS error and add the typed response code as the context.
+fn to_report(error: tss_esapi::Error) -> Report<TpmResponseError> {
+    Report::new(error).change_context(TpmResponseError::from(error))
+}
+
+/// Common TPM response codes.
+///
+/// - <https://trustedcomputinggroup.org/resource/tpm-library-specification/>
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum TpmResponseError {
+    #[error("TPM policy check failed, the PCR values may have changed")]
+    PolicyFail,
+    #[error("TPM authorization failed, the PIN may be incorrect")]
+    AuthFail,
+    #[error("TPM is in dictionary attack lockout")]
+    Lockout,
+    #[error("TPM handle was not found")]
+    Handle,
+    #[error("TPM command failed")]
+    Other,
+}
+
+impl From<tss_esapi::Error> for TpmResponseError {
+    fn from(error: tss_esapi::Error) -> Self {
+        let tss_esapi::Error::Tss2Error(code) = error else {
+            return Self::Other;
+        };
+        match code.kind() {
+            Some(Tss2ResponseCodeKind::PolicyFail | Tss2ResponseCodeKind::PcrChanged) => {
+                Self::PolicyFail
+            }
+            Some(Tss2ResponseCodeKind::AuthFail | Tss2ResponseCodeKind::BadAuth) => Self::AuthFail,
+            Some(Tss2ResponseCodeKind::Lockout) => Self::Lockout,
+            Some(Tss2ResponseCodeKind::Handle) => Self::Handle,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum NativeTpmError {
+    #[error("Unable to create TPM context")]
+    Context,
+    #[error("Unable to start TPM session")]
+    Session,
+    #[error("Unable to apply TPM policy")]
+    Policy,
+    #[error("Invalid TPM handle")]
+    Handle,
+}
@@ -1,20 +1,24 @@
 use crate::prelude::*;
 
-const MAX_PERSISTENT_HANDLES: u32 = 7;
+/// Default maximum number of persistent handles to consider when choosing the next handle.
+pub const DEFAULT_MAX_PERSISTENT_HANDLES: u16 = 7;
 
-#[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+/// Get the first persistent handle that is not in use.
+///
+/// Only the first `max` handles from [`PersistentHandle::BASE`] are considered.
 pub fn next_handle(
     mut handles: Vec<PersistentHandle>,
+    max: u16,
 ) -> Result<PersistentHandle, Report<NextHandleError>> {
     handles.sort_unstable();
-    for i in 0..MAX_PERSISTENT_HANDLES {
-        let candidate =
-            PersistentHandle::from_offset(u16::try_from(i).expect("Offset should fit in u16"));
+    for offset in 0..max {
+        let candidate = PersistentHandle::from_offset(offset);
         if !handles.contains(&candidate) {
             return Ok(candidate);
         }
     }
-    Err(Report::new(NextHandleError))
+    let report = Report::new(NextHandleError).attach_key_value("Max", &max.to_string());
+    Err(report)
 }
 
 #[derive(Clone, Copy, Debug, Error, PartialEq)]
@@ -35,7 +39,8 @@ mod tests {
         ];
 
         // Act
-        let next = next_handle(handles).expect("should be able to get next handle");
+        let next = next_handle(handles, DEFAULT_MAX_PERSISTENT_HANDLES)
+            .expect("should be able to get next handle");
 
         // Assert
         assert_eq!(
@@ -43,4 +48,21 @@ mod tests {
             PersistentHandle::new(0x81000000).expect("handle should be valid")
         );
     }
+
+    #[test]
+    fn _get_next_handle_max() {
+        // Arrange
+        let handles = vec![
+            PersistentHandle::from_offset(0),
+            PersistentHandle::from_offset(1),
+        ];
+
+        // Act
+        let limited = next_handle(handles.clone(), 2);
+        let next = next_handle(handles, 3).expect("should be able to get next handle");
+
+        // Assert
+        assert!(limited.is_err());
+        assert_eq!(next, PersistentHandle::from_offset(2));
+    }
 }
@@ -0,0 +1,162 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+/// Default PCR register index
+///
+/// `7`: Secure Boot State. Contains the full contents of PK/KEK/db, as well as the specific
+/// certificates used to validate each boot application.
+///
+/// - <https://wiki.archlinux.org/title/Trusted_Platform_Module#Accessing_PCR_registers>
+const DEFAULT_PCR_INDEX: u8 = 7;
+
+/// Number of PCR registers in a PC client TPM.
+///
+/// - <https://trustedcomputinggroup.org/resource/pc-client-specific-platform-firmware-profile-specification/>
+const PCR_COUNT: u8 = 24;
+
+/// PCR bank algorithm
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/alg/>
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/pcr/>
+#[derive(Clone, Copy, Debug, Default, Deserialize, Display, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PcrBank {
+    Sha1,
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+/// PCR registers the sealed TPM object is bound to.
+///
+/// The same policy must be used to seal and unseal the object.
+///
+/// Commonly used registers:
+/// - `0`: Core system firmware executable code
+/// - `2`: Extended or pluggable executable code
+/// - `4`: Boot loader code
+/// - `7`: Secure Boot State
+///
+/// - <https://wiki.archlinux.org/title/Trusted_Platform_Module#Accessing_PCR_registers>
+#[derive(Clone, Debug, PartialEq)]
+pub struct PcrPolicy {
+    bank: PcrBank,
+    pcrs: Vec<u8>,
+}
+
+impl PcrPolicy {
+    /// Create a policy from a bank and a list of PCR indices.
+    ///
+    /// The indices are sorted and deduplicated.
+    pub fn new(bank: PcrBank, mut pcrs: Vec<u8>) -> Result<Self, Report<PcrPolicyError>> {
+        if pcrs.is_empty() {
+            bail!(PcrPolicyError::Empty);
+        }
+        if let Some(index) = pcrs.iter().find(|&&index| index >= PCR_COUNT) {
+            let report =
+                Report::new(PcrPolicyError::Index).attach_key_value("Index", &index.to_string());
+            return Err(report);
+        }
+        pcrs.sort_unstable();
+        pcrs.dedup();
+        Ok(Self { bank, pcrs })
+    }
+
+    #[must_use]
+    pub fn bank(&self) -> PcrBank {
+        self.bank
+    }
+
+    #[must_use]
+    pub fn pcrs(&self) -> &[u8] {
+        &self.pcrs
+    }
+}
+
+impl Default for PcrPolicy {
+    fn default() -> Self {
+        Self {
+            bank: PcrBank::default(),
+            pcrs: vec![DEFAULT_PCR_INDEX],
+        }
+    }
+}
+
+/// Format as a PCR selection list.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/pcr/>
+impl Display for PcrPolicy {
+    #[allow(clippy::absolute_paths)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pcrs = self
+            .pcrs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(f, "{}:{pcrs}", self.bank)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum PcrPolicyError {
+    #[error("At least one PCR index is required")]
+    Empty,
+    #[error("PCR index must be less than 24")]
+    Index,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcr_policy_default() {
+        // Arrange
+        // Act
+        let policy = PcrPolicy::default();
+
+        // Assert
+        assert_eq!(policy.to_string(), "sha256:7");
+    }
+
+    #[test]
+    fn pcr_policy_new() {
+        // Arrange
+        let pcrs = vec![7, 0, 4, 2, 7];
+
+        // Act
+        let policy = PcrPolicy::new(PcrBank::Sha384, pcrs).expect("policy should be valid");
+
+        // Assert
+        assert_eq!(policy.to_string(), "sha384:0,2,4,7");
+    }
+
+    #[test]
+    fn pcr_policy_new_invalid() {
+        // Arrange
+        // Act
+        let empty = PcrPolicy::new(PcrBank::Sha256, Vec::new());
+        let index = PcrPolicy::new(PcrBank::Sha256, vec![7, 24]);
+
+        // Assert
+        let empty = empty.expect_err("empty policy should be invalid");
+        assert_eq!(empty.current_context(), &PcrPolicyError::Empty);
+        let index = index.expect_err("index 24 should be invalid");
+        assert_eq!(index.current_context(), &PcrPolicyError::Index);
+    }
+
+    #[test]
+    fn deserialize_pcr_bank() {
+        // Arrange
+        // Act
+        let bank: PcrBank = serde_yaml::from_str("sha384").expect("should deserialize sha384");
+
+        // Assert
+        assert_eq!(bank, PcrBank::Sha384);
+        assert!(serde_yaml::from_str::<PcrBank>("md5").is_err());
+    }
+}
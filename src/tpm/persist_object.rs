@@ -3,8 +3,7 @@ use crate::prelude::*;
 /// Make a transient object persistent.
 ///
 /// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_evictcontrol.1/>
-pub fn persist_object(options: &Options) -> Result<(), Report<PersistObjectError>> {
-    let handle = options.tpm_handle.ok_or(PersistObjectError::Required)?;
+pub fn persist_object(handle: PersistentHandle) -> Result<(), Report<PersistObjectError>> {
     Command::new("tpm2_evictcontrol")
         .arg("--hierarchy")
         .arg(OWNER_HIERARCHY) // Owner hierarchy
@@ -13,17 +12,13 @@ pub fn persist_object(options: &Options) -> Result<(), Report<PersistObjectError
         .arg(handle.to_string())
         .output()
         .expect("should be able to execute `tpm2_evictcontrol`")
-        .ok_or(PersistObjectError::Failed)
+        .ok_or(PersistObjectError)
         .attach_key_value("Handle", &handle.to_string())
 }
 
 #[derive(Clone, Copy, Debug, Error, PartialEq)]
-pub enum PersistObjectError {
-    #[error("The `tpm_handle` option is required")]
-    Required,
-    #[error("Unable to persist object")]
-    Failed,
-}
+#[error("Unable to persist object")]
+pub struct PersistObjectError;
 
 #[cfg(test)]
 mod tests {
@@ -36,15 +31,12 @@ mod tests {
 
         // Arrange
         let handles = get_handles().expect("Should be able to get handles");
-        let handle = next_handle(handles).expect("Should be able to get a handle");
+        let handle = next_handle(handles, DEFAULT_MAX_PERSISTENT_HANDLES)
+            .expect("Should be able to get a handle");
         eprintln!("Using handle: {handle}");
-        let options = Options {
-            tpm_handle: Some(handle),
-            ..Options::default()
-        };
 
         // Act
-        let result = persist_object(&options);
+        let result = persist_object(handle);
         evict_object(handle).expect("Should be able to evict object");
 
         // Preview
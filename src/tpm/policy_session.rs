@@ -0,0 +1,80 @@
+use crate::prelude::*;
+
+/// Start an authorization session.
+///
+/// A trial session is used to calculate a policy digest when sealing an object. A policy
+/// session is used to satisfy the policy when unsealing an object.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_startauthsession.1/>
+pub fn start_session(policy_session: bool) -> Result<(), Report<PolicySessionError>> {
+    let mut command = Command::new("tpm2_startauthsession");
+    if policy_session {
+        command.arg("--policy-session");
+    }
+    command
+        .arg("--session")
+        .arg(TPM_SESSION_PATH.display().to_string())
+        .output()
+        .expect("should be able to execute `tpm2_startauthsession`")
+        .ok_or(PolicySessionError::Start)
+}
+
+/// Extend the session with a PCR policy followed by an auth value policy.
+///
+/// If `output` is provided the resulting policy digest is written to it.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_policypcr.1/>
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_policyauthvalue.1/>
+pub fn apply_pin_policy(
+    policy: &PcrPolicy,
+    output: Option<&Path>,
+) -> Result<(), Report<PolicySessionError>> {
+    let mut command = Command::new("tpm2_policypcr");
+    command
+        .arg("--session")
+        .arg(TPM_SESSION_PATH.display().to_string())
+        .arg("--pcr-list")
+        .arg(policy.to_string());
+    if let Some(path) = output {
+        command.arg("--policy").arg(path.display().to_string());
+    }
+    command
+        .output()
+        .expect("should be able to execute `tpm2_policypcr`")
+        .ok_or(PolicySessionError::Pcr)
+        .attach_key_value("Policy", &policy.to_string())?;
+    let mut command = Command::new("tpm2_policyauthvalue");
+    command
+        .arg("--session")
+        .arg(TPM_SESSION_PATH.display().to_string());
+    if let Some(path) = output {
+        command.arg("--policy").arg(path.display().to_string());
+    }
+    command
+        .output()
+        .expect("should be able to execute `tpm2_policyauthvalue`")
+        .ok_or(PolicySessionError::AuthValue)
+}
+
+/// Remove the session from the TPM.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_flushcontext.1/>
+pub fn flush_session() -> Result<(), Report<PolicySessionError>> {
+    Command::new("tpm2_flushcontext")
+        .arg(TPM_SESSION_PATH.display().to_string())
+        .output()
+        .expect("should be able to execute `tpm2_flushcontext`")
+        .ok_or(PolicySessionError::Flush)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum PolicySessionError {
+    #[error("Unable to start TPM session")]
+    Start,
+    #[error("Unable to apply TPM PCR policy")]
+    Pcr,
+    #[error("Unable to apply TPM auth value policy")]
+    AuthValue,
+    #[error("Unable to flush TPM session")]
+    Flush,
+}
@@ -0,0 +1,100 @@
+use crate::prelude::*;
+use rpassword::prompt_password;
+
+/// Reseal the TPM component of the key under the current PCR values.
+///
+/// Used after a firmware or Secure Boot change when the existing object can no longer be
+/// unsealed.
+///
+/// The new object is sealed at a free handle and checked before the existing object is
+/// evicted, so a mistyped key or a failure while sealing leaves the existing object in place.
+pub fn rotate_tpm_command(options: Options) -> Result<(), AnyReport> {
+    let _temp_files = TempFilesGuard::new();
+    let counter = Mutex::new(0);
+    let total_steps = 9;
+    let backend = tpm_backend();
+
+    print_step_start(&counter, total_steps, "Checking if root");
+    is_root()?;
+    print_step_completed("Access granted");
+
+    print_step_start(&counter, total_steps, "Verifying existing passphrase");
+    let old_handle = options
+        .get_tpm_handle()
+        .ok_or_else(|| Report::new(TpmHandleRequired))?;
+    let passphrase = prompt_password("Enter an existing or recovery passphrase: ")
+        .change_context(KeyError::Prompt)?;
+    let passphrase = Secret::from(passphrase);
+    luks_backend().check_key(&options, &passphrase)?;
+    print_step_completed("Passphrase is valid");
+
+    print_step_start(&counter, total_steps, "Reading TPM component of the key");
+    let policy = options.get_pcr_policy()?;
+    let (key, pin) = prompt_sealing_input(&options)?;
+    print_step_completed("Read TPM component of the key");
+
+    print_step_start(&counter, total_steps, "Sealing key in the TPM");
+    let max = options
+        .max_tpm_handles
+        .unwrap_or(DEFAULT_MAX_PERSISTENT_HANDLES);
+    let handle = next_handle(backend.get_handles()?, max)?;
+    backend.seal(handle, &policy, &key, pin.as_ref())?;
+    print_step_completed(&format!("Sealed key at TPM handle {handle}"));
+
+    print_step_start(&counter, total_steps, "Validating key");
+    let mut options = options;
+    options.tpm_handle = Some(handle.into());
+    let uncached = Options {
+        tpm_cache_timeout: None,
+        ..options.clone()
+    };
+    let result = get_key(&uncached).and_then(|key| luks_backend().check_key(&uncached, &key));
+    if let Err(report) = result {
+        if let Err(evict) = backend.evict(handle) {
+            warn!("Unable to evict new TPM object:\n{evict:?}");
+        }
+        return Err(report
+            .attach(format!("Existing TPM object at {old_handle} was kept"))
+            .into());
+    }
+    print_step_completed("Key is valid");
+
+    print_step_start(&counter, total_steps, "Evicting existing TPM object");
+    if backend.get_handles()?.contains(&old_handle) {
+        backend.evict(old_handle)?;
+        print_step_completed(&format!("Evicted existing TPM object at {old_handle}"));
+    } else {
+        print_step_completed("Skipped as TPM handle is not in use");
+    }
+
+    print_step_start(&counter, total_steps, "Saving TPM handle");
+    options.save_option("tpm_handle", &handle.to_string())?;
+    print_step_completed("Saved TPM handle to options file");
+
+    print_step_start(&counter, total_steps, "Caching TPM key in keyring");
+    if let Some(timeout) = options.tpm_cache_timeout {
+        let description = get_tpm_cache_description(&options);
+        add_keyring_key(&description, &key.trim(), timeout)?;
+        print_step_completed(&format!("Cached TPM key for {timeout} seconds"));
+    } else {
+        print_step_completed("Skipped as `tpm_cache_timeout` is not set");
+    }
+
+    print_step_start(&counter, total_steps, "Updating LUKS2 token");
+    match save_luks_token(&options, None) {
+        Ok(keyslots) if keyslots.is_empty() => {
+            print_step_completed("Skipped as there is no LUKS2 token, run `set-luks` to add one");
+        }
+        Ok(_) => print_step_completed("Updated TPM handle in LUKS2 token"),
+        Err(report) => {
+            warn!("Unable to update LUKS2 token:\n{report:?}");
+            print_step_completed("Skipped as the LUKS2 token could not be updated");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[error("The `tpm_handle` option is required")]
+pub struct TpmHandleRequired;
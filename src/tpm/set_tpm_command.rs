@@ -1,36 +1,68 @@
 use crate::prelude::*;
 
-pub fn set_tpm_command(options: Options) -> Result<(), AnyReport> {
+pub fn set_tpm_command(mut options: Options) -> Result<(), AnyReport> {
+    let _temp_files = TempFilesGuard::new();
     let counter = Mutex::new(0);
     let total_steps = 7;
+    let backend = tpm_backend();
 
     print_step_start(&counter, total_steps, "Checking if root");
     is_root()?;
     print_step_completed("Access granted");
 
     print_step_start(&counter, total_steps, "Checking TPM handle");
-    check_handle(&options)?;
-    print_step_completed("TPM handle is available");
+    let is_auto = options.get_tpm_handle().is_none();
+    let handle = if let Some(handle) = options.get_tpm_handle() {
+        check_handle(&options)?;
+        print_step_completed("TPM handle is available");
+        handle
+    } else {
+        let max = options
+            .max_tpm_handles
+            .unwrap_or(DEFAULT_MAX_PERSISTENT_HANDLES);
+        let handle = next_handle(backend.get_handles()?, max)?;
+        options.tpm_handle = Some(handle.into());
+        print_step_completed(&format!("Chose available TPM handle {handle}"));
+        handle
+    };
 
-    print_step_start(&counter, total_steps, "Creating TPM PCR policy");
-    create_policy()?;
-    print_step_completed("Created TPM PCR policy");
+    print_step_start(&counter, total_steps, "Reading TPM component of the key");
+    let policy = options.get_pcr_policy()?;
+    let (key, pin) = prompt_sealing_input(&options)?;
+    print_step_completed("Read TPM component of the key");
 
-    print_step_start(&counter, total_steps, "Creating TPM primary key");
-    create_primary()?;
-    print_step_completed("Created TPM primary key");
+    print_step_start(&counter, total_steps, "Sealing key in the TPM");
+    backend.seal(handle, &policy, &key, pin.as_ref())?;
+    print_step_completed(&format!("Sealed key at TPM handle {handle}"));
 
-    print_step_start(&counter, total_steps, "Creating TPM object");
-    create_object()?;
-    print_step_completed("Created TPM object");
+    print_step_start(&counter, total_steps, "Caching TPM key in keyring");
+    if let Some(timeout) = options.tpm_cache_timeout {
+        let description = get_tpm_cache_description(&options);
+        add_keyring_key(&description, &key.trim(), timeout)?;
+        print_step_completed(&format!("Cached TPM key for {timeout} seconds"));
+    } else {
+        print_step_completed("Skipped as `tpm_cache_timeout` is not set");
+    }
 
-    print_step_start(&counter, total_steps, "Loading object into TPM");
-    load_object()?;
-    print_step_completed("Loaded object into TPM");
+    print_step_start(&counter, total_steps, "Saving TPM handle");
+    if is_auto {
+        options.save_option("tpm_handle", &handle.to_string())?;
+        print_step_completed("Saved TPM handle to options file");
+    } else {
+        print_step_completed("Skipped as TPM handle was already set");
+    }
 
-    print_step_start(&counter, total_steps, "Making TPM object persistent");
-    persist_object(&options)?;
-    print_step_completed("Made TPM object persistent");
+    print_step_start(&counter, total_steps, "Updating LUKS2 token");
+    match save_luks_token(&options, None) {
+        Ok(keyslots) if keyslots.is_empty() => {
+            print_step_completed("Skipped as there is no LUKS2 token, run `set-luks` to add one");
+        }
+        Ok(_) => print_step_completed("Updated TPM policy in LUKS2 token"),
+        Err(report) => {
+            warn!("Unable to update LUKS2 token:\n{report:?}");
+            print_step_completed("Skipped as the LUKS2 token could not be updated");
+        }
+    }
 
     Ok(())
 }
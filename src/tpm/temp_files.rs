@@ -0,0 +1,141 @@
+use crate::prelude::*;
+use std::fs::{OpenOptions, read_dir, remove_dir, remove_file, symlink_metadata};
+use std::io::{ErrorKind, Write};
+
+/// Securely remove the TPM temp files when dropped.
+///
+/// Hold this while sealing so the sealed object isn't left on disk whether the command
+/// finishes or fails.
+pub struct TempFilesGuard;
+
+impl TempFilesGuard {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Drop for TempFilesGuard {
+    fn drop(&mut self) {
+        if let Err(report) = remove_temp_files() {
+            warn!("Unable to remove TPM temp files:\n{report:?}");
+        }
+    }
+}
+
+/// Overwrite and remove every file in the temp directory.
+///
+/// Does nothing if the temp directory hasn't been created.
+pub fn remove_temp_files() -> Result<(), Report<TempFilesError>> {
+    let Some(dir) = TEMP_DIR.get() else {
+        return Ok(());
+    };
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => {
+            return Err(Report::new(error)
+                .change_context(TempFilesError::Read)
+                .attach_path(dir));
+        }
+    };
+    for entry in entries {
+        let path = entry
+            .change_context(TempFilesError::Read)
+            .attach_path(dir)?
+            .path();
+        remove_temp_file(&path)?;
+    }
+    Ok(())
+}
+
+/// Remove the temp directory and its files.
+///
+/// Does nothing if the temp directory hasn't been created.
+pub fn remove_temp_dir() -> Result<(), Report<TempFilesError>> {
+    remove_temp_files()?;
+    let Some(dir) = TEMP_DIR.get() else {
+        return Ok(());
+    };
+    match remove_dir(dir) {
+        Err(error) if error.kind() != ErrorKind::NotFound => Err(Report::new(error)
+            .change_context(TempFilesError::Remove)
+            .attach_path(dir)),
+        _ => Ok(()),
+    }
+}
+
+/// Overwrite a regular file with zeros before removing it.
+///
+/// Symlinks are removed without being followed.
+fn remove_temp_file(path: &Path) -> Result<(), Report<TempFilesError>> {
+    let metadata = symlink_metadata(path)
+        .change_context(TempFilesError::Read)
+        .attach_path(path)?;
+    if metadata.is_file() {
+        let length = usize::try_from(metadata.len()).expect("Temp file length should fit in usize");
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .change_context(TempFilesError::Overwrite)
+            .attach_path(path)?;
+        file.write_all(&vec![0; length])
+            .and_then(|()| file.sync_all())
+            .change_context(TempFilesError::Overwrite)
+            .attach_path(path)?;
+    }
+    remove_file(path)
+        .change_context(TempFilesError::Remove)
+        .attach_path(path)
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum TempFilesError {
+    #[error("Unable to read TPM temp directory")]
+    Read,
+    #[error("Unable to overwrite TPM temp file")]
+    Overwrite,
+    #[error("Unable to remove TPM temp file")]
+    Remove,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{hard_link, read, write};
+
+    #[test]
+    fn remove_temp_file_overwrites() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("Should be able to create temp dir");
+        let path = dir.join("object.private");
+        let link = dir.join("link");
+        write(&path, b"sealed").expect("Should be able to write temp file");
+        hard_link(&path, &link).expect("Should be able to link temp file");
+
+        // Act
+        remove_temp_file(&path).expect("Should be able to remove temp file");
+
+        // Assert
+        assert!(!path.exists());
+        let content = read(&link).expect("Should be able to read link");
+        assert_eq!(content, [0; 6]);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn temp_files_guard_drop() {
+        // Arrange
+        let path = TPM_OBJ_PRIVATE_PATH.as_path();
+        write(path, b"sealed").expect("Should be able to write temp file");
+        let guard = TempFilesGuard::new();
+
+        // Act
+        drop(guard);
+
+        // Assert
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::prelude::*;
+
+/// Operations on the TPM used to seal and unseal the TPM component of the key.
+///
+/// The implementation is chosen at compile time by the `native-tpm` feature.
+pub trait TpmBackend {
+    /// Get handles of persistent objects.
+    fn get_handles(&self) -> Result<Vec<PersistentHandle>, Report<CheckHandleError>>;
+
+    /// Seal `key` under the PCR policy and persist the object at `handle`.
+    ///
+    /// If `pin` is provided it is also required to unseal the object.
+    fn seal(
+        &self,
+        handle: PersistentHandle,
+        policy: &PcrPolicy,
+        key: &Secret,
+        pin: Option<&Secret>,
+    ) -> Result<(), Report<SealError>>;
+
+    /// Unseal the persistent object at `handle`.
+    fn unseal(
+        &self,
+        handle: PersistentHandle,
+        policy: &PcrPolicy,
+        pin: Option<&Secret>,
+    ) -> Result<Secret, Report<UnsealError>>;
+
+    /// Evict the persistent object at `handle`.
+    fn evict(&self, handle: PersistentHandle) -> Result<(), Report<EvictObjectError>>;
+}
+
+/// Get the TPM backend chosen by the `native-tpm` feature.
+#[must_use]
+pub fn tpm_backend() -> impl TpmBackend {
+    #[cfg(feature = "native-tpm")]
+    {
+        NativeTpmBackend
+    }
+    #[cfg(not(feature = "native-tpm"))]
+    {
+        SubprocessTpmBackend
+    }
+}
+
+/// TPM backend that executes `tpm2-tools`.
+///
+/// Intermediate objects are written to the temp directory.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/>
+pub struct SubprocessTpmBackend;
+
+impl TpmBackend for SubprocessTpmBackend {
+    fn get_handles(&self) -> Result<Vec<PersistentHandle>, Report<CheckHandleError>> {
+        get_handles()
+    }
+
+    fn seal(
+        &self,
+        handle: PersistentHandle,
+        policy: &PcrPolicy,
+        key: &Secret,
+        pin: Option<&Secret>,
+    ) -> Result<(), Report<SealError>> {
+        create_policy(policy, pin.is_some()).change_context(SealError)?;
+        create_primary().change_context(SealError)?;
+        create_object_from_input(key, pin).change_context(SealError)?;
+        load_object().change_context(SealError)?;
+        persist_object(handle).change_context(SealError)
+    }
+
+    fn unseal(
+        &self,
+        handle: PersistentHandle,
+        policy: &PcrPolicy,
+        pin: Option<&Secret>,
+    ) -> Result<Secret, Report<UnsealError>> {
+        unseal_persistent_object(&handle, policy, pin)
+    }
+
+    fn evict(&self, handle: PersistentHandle) -> Result<(), Report<EvictObjectError>> {
+        evict_object(handle)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+#[error("Unable to seal key in the TPM")]
+pub struct SealError;
@@ -1,30 +1,10 @@
 use crate::prelude::*;
-use std::env::temp_dir;
-use std::fs::create_dir;
-use std::sync::LazyLock;
+use std::sync::{LazyLock, OnceLock};
 
 #[cfg(test)]
 pub static EXAMPLE_HANDLE: LazyLock<PersistentHandle> =
     LazyLock::new(|| PersistentHandle::new(0x8100FFFF).expect("valid handle"));
 
-/// PCR bank algorithm
-///
-/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/alg/>
-/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/pcr/>
-const BANK_ALGORITHM: &str = "sha256";
-
-/// PCR register index
-///
-/// `7`: Secure Boot State. Contains the full contents of PK/KEK/db, as well as the specific
-/// certificates used to validate each boot application.
-///
-/// - <https://wiki.archlinux.org/title/Trusted_Platform_Module#Accessing_PCR_registers>
-const PCR_INDEX: u8 = 7;
-
-/// PCR policy
-/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/pcr/>
-pub static POLICY: LazyLock<String> = LazyLock::new(|| format!("{BANK_ALGORITHM}:{PCR_INDEX}"));
-
 /// The hierarchy under which the object is created.
 ///
 /// This will also dictate which authorization secret (if any) must be supplied.
@@ -45,30 +25,45 @@ pub const OWNER_HIERARCHY: &str = "o";
 /// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_create.1/>
 pub const HASH_ALGORITHM: &str = "sha256";
 
-/// Get the app specific temp directory.
+/// Private temp directory of this process.
 ///
-/// The directory is created if it does not already exist.
-static TEMP_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
-    let dir = temp_dir().join(APP_NAME);
-    if !dir.exists() {
-        create_dir(&dir).expect("should be able to create temp dir");
-    }
-    dir
-});
+/// Only set once the directory has been created so it can be removed on exit.
+pub(crate) static TEMP_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Get the private temp directory of this process.
+///
+/// The directory is created on first use.
+fn get_temp_dir() -> &'static PathBuf {
+    TEMP_DIR.get_or_init(|| create_private_dir().expect("should be able to create temp dir"))
+}
 
 /// Path of the PCR policy type.
-pub static TPM_POLICY_PATH: LazyLock<PathBuf> = LazyLock::new(|| TEMP_DIR.join("policy.dat"));
+pub static TPM_POLICY_PATH: LazyLock<PathBuf> = LazyLock::new(|| get_temp_dir().join("policy.dat"));
 
 /// Path of the TPM primary object context.
 pub static TPM_PRIMARY_CONTEXT_PATH: LazyLock<PathBuf> =
-    LazyLock::new(|| TEMP_DIR.join("primary.ctx"));
+    LazyLock::new(|| get_temp_dir().join("primary.ctx"));
 
 /// Path of the public portion of the TPM sealed object.
-pub static TPM_OBJ_PUBLIC_PATH: LazyLock<PathBuf> = LazyLock::new(|| TEMP_DIR.join("object.pub"));
+pub static TPM_OBJ_PUBLIC_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| get_temp_dir().join("object.pub"));
 
 /// Path of the sensitive portion of the TPM sealed object.
 pub static TPM_OBJ_PRIVATE_PATH: LazyLock<PathBuf> =
-    LazyLock::new(|| TEMP_DIR.join("object.private"));
+    LazyLock::new(|| get_temp_dir().join("object.private"));
+
+/// Path of the TPM authorization session context.
+pub static TPM_SESSION_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| get_temp_dir().join("session.ctx"));
+
+/// Attributes of a sealed object that is protected by a PIN.
+///
+/// `userwithauth` is deliberately omitted so the PIN alone can't satisfy the authorization
+/// without the PCR policy.
+///
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/obj-attrs/>
+pub const PIN_OBJECT_ATTRIBUTES: &str = "fixedtpm|fixedparent|adminwithpolicy";
 
 /// Path of the TPM sealed object context.
-pub static TPM_OBJ_CONTEXT_PATH: LazyLock<PathBuf> = LazyLock::new(|| TEMP_DIR.join("object.ctx"));
+pub static TPM_OBJ_CONTEXT_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| get_temp_dir().join("object.ctx"));
@@ -0,0 +1,69 @@
+use crate::prelude::*;
+
+/// Evict a persistent TPM object after confirmation.
+///
+/// Refuses to evict a handle that a configured volume depends on unless `force` is set.
+pub fn tpm_evict_command(
+    volumes: Vec<Options>,
+    handle: PersistentHandle,
+    force: bool,
+) -> Result<(), AnyReport> {
+    let counter = Mutex::new(0);
+    let total_steps = 5;
+
+    print_step_start(&counter, total_steps, "Checking if root");
+    is_root()?;
+    print_step_completed("Access granted");
+
+    print_step_start(&counter, total_steps, "Checking TPM handle");
+    if !tpm_backend().get_handles()?.contains(&handle) {
+        let report =
+            Report::new(TpmEvictError::NotFound).attach_key_value("Handle", &handle.to_string());
+        return Err(report.into());
+    }
+    print_step_completed("TPM handle is in use");
+
+    print_step_start(&counter, total_steps, "Checking configured volumes");
+    let names = get_dependent_volumes(&volumes, handle);
+    if names.is_empty() {
+        print_step_completed("No configured volume depends on the TPM handle");
+    } else if force {
+        warn!(
+            "Forcing eviction of a TPM handle used by: {}",
+            names.join(", ")
+        );
+        print_step_completed("Skipped as eviction is forced");
+    } else {
+        let report = Report::new(TpmEvictError::InUse)
+            .attach_key_value("Handle", &handle.to_string())
+            .attach_key_value("Volumes", &names.join(", "))
+            .attach("Use `--force` to evict anyway");
+        return Err(report.into());
+    }
+
+    print_step_start(&counter, total_steps, "Confirming eviction");
+    let confirmed = prompt_confirm(&format!("Evict TPM handle {handle}?"))
+        .change_context(TpmEvictError::Prompt)?;
+    if !confirmed {
+        return Err(Report::new(TpmEvictError::Cancelled).into());
+    }
+    print_step_completed("Eviction confirmed");
+
+    print_step_start(&counter, total_steps, "Evicting TPM object");
+    tpm_backend().evict(handle)?;
+    print_step_completed("Evicted TPM object");
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum TpmEvictError {
+    #[error("TPM handle is not in use")]
+    NotFound,
+    #[error("TPM handle is used by a configured volume")]
+    InUse,
+    #[error("Unable to read confirmation")]
+    Prompt,
+    #[error("Eviction was cancelled")]
+    Cancelled,
+}
@@ -0,0 +1,163 @@
+use crate::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Value of the `tpm_handle` option to choose a handle automatically.
+const AUTO: &str = "auto";
+
+/// Value of the `tpm_handle` option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TpmHandle {
+    /// Choose the first available persistent handle when the object is persisted.
+    Auto,
+    /// A specific persistent handle.
+    Persistent(PersistentHandle),
+}
+
+impl TpmHandle {
+    /// Get the persistent handle if one has been assigned.
+    #[must_use]
+    pub fn persistent(self) -> Option<PersistentHandle> {
+        match self {
+            TpmHandle::Auto => None,
+            TpmHandle::Persistent(handle) => Some(handle),
+        }
+    }
+}
+
+impl From<PersistentHandle> for TpmHandle {
+    fn from(handle: PersistentHandle) -> Self {
+        Self::Persistent(handle)
+    }
+}
+
+impl Display for TpmHandle {
+    #[allow(clippy::absolute_paths)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TpmHandle::Auto => write!(f, "{AUTO}"),
+            TpmHandle::Persistent(handle) => write!(f, "{handle}"),
+        }
+    }
+}
+
+impl FromStr for TpmHandle {
+    type Err = PersistentHandleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case(AUTO) {
+            Ok(Self::Auto)
+        } else {
+            s.parse().map(Self::Persistent)
+        }
+    }
+}
+
+impl Serialize for TpmHandle {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TpmHandle {
+    #[allow(clippy::absolute_paths)]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match RawTpmHandle::deserialize(deserializer)? {
+            RawTpmHandle::Number(handle) => PersistentHandle::new(handle).map(Self::Persistent),
+            RawTpmHandle::Text(s) => s.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Value of the `tpm_handle` option as written in an options file.
+///
+/// A hex handle is read as a number when the volume is deserialized from a YAML value.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTpmHandle {
+    Number(u32),
+    Text(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_tpm_handle_auto() {
+        // Arrange
+        let yaml = "auto";
+
+        // Act
+        let handle: TpmHandle = serde_yaml::from_str(yaml).expect("Should be able to deserialize");
+
+        // Assert
+        assert_eq!(handle, TpmHandle::Auto);
+        assert_eq!(handle.persistent(), None);
+    }
+
+    #[test]
+    fn deserialize_tpm_handle_persistent() {
+        // Arrange
+        let yaml = "0x81000003";
+
+        // Act
+        let handle: TpmHandle = serde_yaml::from_str(yaml).expect("Should be able to deserialize");
+
+        // Assert
+        assert_eq!(
+            handle,
+            TpmHandle::Persistent(PersistentHandle::from_offset(3))
+        );
+        assert_eq!(handle.persistent(), Some(PersistentHandle::from_offset(3)));
+    }
+
+    #[test]
+    fn deserialize_tpm_handle_number() {
+        // Arrange
+        let value = serde_yaml::Value::from(0x8100_0003_u32);
+
+        // Act
+        let handle: TpmHandle =
+            serde_yaml::from_value(value).expect("Should be able to deserialize");
+
+        // Assert
+        assert_eq!(
+            handle,
+            TpmHandle::Persistent(PersistentHandle::from_offset(3))
+        );
+    }
+
+    #[test]
+    fn deserialize_tpm_handle_invalid() {
+        // Arrange
+        let yaml = "automatic";
+
+        // Act
+        let result = serde_yaml::from_str::<TpmHandle>(yaml);
+
+        // Assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn _to_string() {
+        // Arrange
+        let auto = TpmHandle::Auto;
+        let persistent = TpmHandle::from(PersistentHandle::from_offset(1));
+
+        // Act
+        let auto = auto.to_string();
+        let persistent = persistent.to_string();
+
+        // Assert
+        assert_eq!(auto, "auto");
+        assert_eq!(persistent, "0x81000001");
+    }
+}
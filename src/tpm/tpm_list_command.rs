@@ -0,0 +1,80 @@
+use crate::prelude::*;
+
+const HEADERS: [&str; 3] = ["Handle", "Present", "Volumes"];
+
+/// List the persistent TPM handles and the configured volumes that reference them.
+///
+/// Handles referenced by a volume that are not present in the TPM are also listed.
+pub fn tpm_list_command(volumes: Vec<Options>) -> Result<(), AnyReport> {
+    is_root()?;
+    let mut handles = tpm_backend().get_handles()?;
+    let present = handles.clone();
+    for handle in volumes.iter().filter_map(Options::get_tpm_handle) {
+        if !handles.contains(&handle) {
+            handles.push(handle);
+        }
+    }
+    handles.sort_unstable();
+    let rows: Vec<Vec<String>> = handles
+        .iter()
+        .map(|handle| {
+            let names = get_dependent_volumes(&volumes, *handle);
+            let names = if names.is_empty() {
+                "-".to_owned()
+            } else {
+                names.join(", ")
+            };
+            let present = if present.contains(handle) {
+                "yes"
+            } else {
+                "no"
+            };
+            vec![handle.to_string(), present.to_owned(), names]
+        })
+        .collect();
+    print_table(&HEADERS, &rows);
+    Ok(())
+}
+
+/// Get the names of the volumes that depend on a TPM handle.
+#[must_use]
+pub fn get_dependent_volumes(volumes: &[Options], handle: PersistentHandle) -> Vec<String> {
+    volumes
+        .iter()
+        .filter(|options| options.get_tpm_handle() == Some(handle))
+        .map(|options| options.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn _get_dependent_volumes() {
+        // Arrange
+        let handle = PersistentHandle::from_offset(1);
+        let volumes = vec![
+            Options {
+                name: "a".to_owned(),
+                tpm_handle: Some(handle.into()),
+                ..Options::default()
+            },
+            Options {
+                name: "b".to_owned(),
+                tpm_handle: Some(PersistentHandle::from_offset(2).into()),
+                ..Options::default()
+            },
+            Options {
+                name: "c".to_owned(),
+                ..Options::default()
+            },
+        ];
+
+        // Act
+        let names = get_dependent_volumes(&volumes, handle);
+
+        // Assert
+        assert_eq!(names, vec!["a".to_owned()]);
+    }
+}
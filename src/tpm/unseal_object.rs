@@ -1,39 +1,85 @@
 use crate::prelude::*;
+use std::process::{Output, Stdio};
 
 /// Unseal a peristent TPM object from its handle.
 ///
 /// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_unseal.1/>
-pub fn unseal_persistent_object(handle: &PersistentHandle) -> Result<String, Report<UnsealError>> {
+pub fn unseal_persistent_object(
+    handle: &PersistentHandle,
+    policy: &PcrPolicy,
+    pin: Option<&Secret>,
+) -> Result<Secret, Report<UnsealError>> {
     let context = handle.to_string();
-    unseal_object(&context)
+    unseal_object(&context, policy, pin)
 }
 
 /// Unseal a TPM object from its context path.
 ///
 /// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_unseal.1/>
 #[cfg(test)]
-pub(crate) fn unseal_object_from_context_path() -> Result<String, Report<UnsealError>> {
+pub(crate) fn unseal_object_from_context_path(
+    policy: &PcrPolicy,
+    pin: Option<&Secret>,
+) -> Result<Secret, Report<UnsealError>> {
     let context = TPM_OBJ_CONTEXT_PATH.display().to_string();
-    unseal_object(&context)
+    unseal_object(&context, policy, pin)
 }
 
 /// Unseal a TPM object from its context.
 ///
+/// If `pin` is provided the PCR policy and auth value are satisfied in a policy session and
+/// the PIN is written to stdin so the TPM dictionary attack protection applies.
+///
 /// - <https://tpm2-tools.readthedocs.io/en/latest/man/tpm2_unseal.1/>
-pub fn unseal_object(context: &str) -> Result<String, Report<UnsealError>> {
-    let response = Command::new("tpm2_unseal")
+/// - <https://tpm2-tools.readthedocs.io/en/latest/man/common/authorizations/>
+pub fn unseal_object(
+    context: &str,
+    policy: &PcrPolicy,
+    pin: Option<&Secret>,
+) -> Result<Secret, Report<UnsealError>> {
+    let (key, output) = if let Some(pin) = pin {
+        start_session(true).change_context(UnsealError)?;
+        let output = apply_pin_policy(policy, None)
+            .change_context(UnsealError)
+            .map(|()| unseal_with_pin(context, pin));
+        flush_session().change_context(UnsealError)?;
+        output?
+    } else {
+        Command::new("tpm2_unseal")
+            .arg("--object-context")
+            .arg(context)
+            .arg("--auth")
+            .arg(format!("pcr:{policy}"))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("should be able to spawn `tpm2_unseal`")
+            .wait_with_secret()
+            .expect("should be able to wait on `tpm2_unseal`")
+    };
+    if !output.status.success() {
+        let report = Report::new(UnsealError)
+            .attach_key_value("Policy", &policy.to_string())
+            .attach_response(output.to_response());
+        return Err(report);
+    }
+    Ok(key.trim())
+}
+
+fn unseal_with_pin(context: &str, pin: &Secret) -> (Secret, Output) {
+    Command::new("tpm2_unseal")
         .arg("--object-context")
         .arg(context)
         .arg("--auth")
-        .arg(format!("pcr:{}", POLICY.to_owned()))
-        .output()
-        .expect("should be able to execute `tpm2_unseal`")
-        .to_response();
-    if !response.status.success() {
-        return Err(Report::new(UnsealError).attach_response(response));
-    }
-    let stdout = response.output.unwrap_or_default();
-    Ok(stdout)
+        .arg(format!("session:{}+file:-", TPM_SESSION_PATH.display()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("should be able to spawn `tpm2_unseal`")
+        .write_to_stdin(pin.expose())
+        .wait_with_secret()
+        .expect("should be able to wait on `tpm2_unseal`")
 }
 
 #[derive(Clone, Copy, Debug, Error, PartialEq)]
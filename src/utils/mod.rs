@@ -3,7 +3,10 @@ mod error;
 mod is_root;
 mod logging;
 mod options;
+mod private_dir;
+mod private_file;
 mod response;
+mod secret;
 #[cfg(test)]
 mod temp_directory;
 mod ui;
@@ -13,7 +16,10 @@ pub use error::*;
 pub use is_root::*;
 pub use logging::*;
 pub use options::*;
+pub use private_dir::*;
+pub use private_file::*;
 pub use response::*;
+pub use secret::*;
 #[cfg(test)]
 pub use temp_directory::*;
 pub use ui::*;
@@ -1,10 +1,22 @@
 use crate::prelude::*;
 use dirs::config_dir;
 use serde::Deserialize;
-use std::fs::{File, read_dir};
+use serde_yaml::Value;
+use std::fs::{File, read_dir, read_to_string};
+
+/// Key of the map of named volumes in an options file.
+const VOLUMES_KEY: &str = "volumes";
 
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct Options {
+    /// Name of the volume
+    ///
+    /// This is the key in the `volumes` map, or the file stem of a single volume options file.
+    #[serde(skip)]
+    pub name: String,
+    /// Path of the options file the volume was read from
+    #[serde(skip)]
+    pub config_path: PathBuf,
     /// Path of the LUKS partition
     ///
     /// Example: `/dev/nvme0n1p9`
@@ -23,63 +35,367 @@ pub struct Options {
     ///
     /// Example: `/root/.config/mount-luks/e.key`
     pub key_path: Option<PathBuf>,
+    /// Optional number of bytes to skip at the start of the key file
+    ///
+    /// Defaults to `0`.
+    pub key_offset: Option<u64>,
+    /// Optional number of bytes to read from the key file
+    ///
+    /// Defaults to the rest of the file.
+    pub key_size: Option<usize>,
+    /// Optional encoding of the key file
+    ///
+    /// Defaults to `text` which removes leading and trailing whitespace.
+    ///
+    /// Examples: `text`, `raw`, `hex`, `base64`
+    pub key_encoding: Option<KeyEncoding>,
+    /// Optional removable device that holds the key file
+    ///
+    /// If set then `key_path` is relative to the root of the device, which is mounted
+    /// read-only while the key is read.
+    ///
+    /// Examples: `uuid: 1234-ABCD`, `label: KEYS`
+    pub key_device: Option<KeyDevice>,
+    /// Optional number of seconds to wait for the key device to appear
+    ///
+    /// Defaults to `30`.
+    pub key_device_timeout: Option<u64>,
+    /// Optional should the key file be refused unless it's on a removable disk?
+    pub require_removable_key: Option<bool>,
+    /// Optional should the key file be read even if it isn't owned by root or is accessible
+    /// by group or others?
+    pub allow_insecure_key_file: Option<bool>,
     /// Optional TPM persistent handle address
     ///
-    /// Example: `0x81000000`
-    pub tpm_handle: Option<PersistentHandle>,
+    /// If `auto` then `set-tpm` chooses the first available handle and saves it to the
+    /// options file.
+    ///
+    /// Examples: `0x81000000`, `auto`
+    pub tpm_handle: Option<TpmHandle>,
+    /// Optional maximum number of persistent handles to consider when choosing a handle
+    ///
+    /// Defaults to `7`.
+    pub max_tpm_handles: Option<u16>,
+    /// Optional should the TPM object require a PIN to unseal?
+    ///
+    /// The PIN is combined with the PCR policy so the TPM dictionary attack protection
+    /// rate-limits guesses.
+    pub tpm_pin: Option<bool>,
+    /// Optional PCR indices the TPM object is sealed against
+    ///
+    /// Defaults to `[7]`, the Secure Boot state.
+    ///
+    /// Example: `[0, 2, 4, 7]`
+    pub pcrs: Option<Vec<u8>>,
+    /// Optional PCR bank algorithm
+    ///
+    /// Defaults to `sha256`.
+    ///
+    /// Examples: `sha256`, `sha384`
+    pub pcr_bank: Option<PcrBank>,
     /// Optional should an interactive key be required?
     pub key_prompt: Option<bool>,
+    /// Optional description of a key in the user keyring to use as a key component
+    ///
+    /// Example: `mount-luks:e`
+    pub keyring: Option<String>,
+    /// Optional name of a systemd credential to use as a key component
+    ///
+    /// The credential is read from `$CREDENTIALS_DIRECTORY` so this requires running as a
+    /// systemd service with `LoadCredential=` or `LoadCredentialEncrypted=`.
+    ///
+    /// Example: `mount-luks.key`
+    pub credential: Option<String>,
+    /// Optional URL of a Tang server to use as a key component
+    ///
+    /// The component can only be recovered while the Tang server is reachable, so the volume
+    /// only unlocks unattended on the network the server is on.
+    ///
+    /// Example: `http://tang.lan`
+    pub tang_url: Option<String>,
+    /// Optional SHA-256 thumbprint of the trusted Tang signing key
+    ///
+    /// If not set then `set-tang` shows the advertised thumbprints and saves the confirmed one.
+    pub tang_thumbprint: Option<String>,
+    /// Optional number of seconds to cache the TPM component in the user keyring
+    ///
+    /// If set then the cached component is used instead of unsealing the TPM object until it
+    /// expires.
+    pub tpm_cache_timeout: Option<u64>,
+    /// Optional method of combining the key components into the LUKS passphrase
+    ///
+    /// Defaults to `concat` so that existing keyslots continue to work.
+    ///
+    /// Examples: `concat`, `hkdf-sha256-v1`
+    pub key_derivation: Option<KeyDerivation>,
+    /// Optional number of key sources required to unlock the volume
+    ///
+    /// If set then `set-luks` splits a random volume secret into one share per key source and
+    /// any `key_threshold` of the sources can unlock the volume. `key_derivation` is ignored.
+    ///
+    /// Example: `2`
+    pub key_threshold: Option<u8>,
+    /// Optional path of the file holding the wrapped shares of each key source
+    ///
+    /// Defaults to `<volume>.shares` next to the options file.
+    pub key_shares_path: Option<PathBuf>,
+    /// Optional LUKS keyslot that holds the mount-luks key
+    ///
+    /// Saved by `set-luks` so `remove-luks` only removes the keyslot that belongs to
+    /// mount-luks.
+    ///
+    /// Example: `1`
+    pub key_slot: Option<u32>,
     /// Hide the UI header
     pub no_header: Option<bool>,
 }
 
 impl Options {
-    pub fn read_options(config_path: Option<PathBuf>) -> Result<Options, Report<OptionsError>> {
-        let path = match config_path {
-            Some(path) => path,
-            None => get_default_config_path()?,
+    /// Read the options of a single volume.
+    ///
+    /// If `volume` is `None` then there must be exactly one configured volume.
+    pub fn read_options(
+        config_path: Option<PathBuf>,
+        volume: Option<&str>,
+    ) -> Result<Options, Report<OptionsError>> {
+        let volumes = Self::read_all(config_path)?;
+        select_volume(volumes, volume)
+    }
+
+    /// Read the options of every configured volume.
+    ///
+    /// If `config_path` is `None` then every options file in the config directory is read.
+    pub fn read_all(config_path: Option<PathBuf>) -> Result<Vec<Options>, Report<OptionsError>> {
+        let paths = match config_path {
+            Some(path) => vec![path],
+            None => get_paths()?,
         };
-        trace!(path = %path.display(), "Reading options from path");
-        let file = File::open(&path)
-            .change_context(OptionsError::Read)
-            .attach_path(&path)?;
-        serde_yaml::from_reader(file)
-            .change_context(OptionsError::Deserialize)
-            .attach_path(&path)
+        trace!(
+            "Found {} options files:\n{}",
+            paths.len(),
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        if paths.is_empty() {
+            bail!(OptionsError::NoFile);
+        }
+        let mut volumes: Vec<Options> = Vec::new();
+        for path in &paths {
+            for options in read_options_file(path)? {
+                if volumes.iter().any(|existing| existing.name == options.name) {
+                    let report = Report::new(OptionsError::DuplicateVolume)
+                        .attach_key_value("Volume", &options.name)
+                        .attach_path(path);
+                    return Err(report);
+                }
+                volumes.push(options);
+            }
+        }
+        Ok(volumes)
     }
 
     pub fn get_mapper_path(&self) -> PathBuf {
         PathBuf::from("/dev/mapper").join(&self.mapper_name)
     }
+
+    /// Get the TPM persistent handle if one has been assigned.
+    #[must_use]
+    pub fn get_tpm_handle(&self) -> Option<PersistentHandle> {
+        self.tpm_handle.and_then(TpmHandle::persistent)
+    }
+
+    /// Get the path of the file holding the wrapped shares of each key source.
+    #[must_use]
+    pub fn get_key_shares_path(&self) -> PathBuf {
+        self.key_shares_path.clone().unwrap_or_else(|| {
+            self.config_path
+                .with_file_name(format!("{}.shares", self.name))
+        })
+    }
+
+    /// Get the path of the file holding the binding to the Tang server.
+    #[must_use]
+    pub fn get_tang_binding_path(&self) -> PathBuf {
+        self.config_path
+            .with_file_name(format!("{}.tang", self.name))
+    }
+
+    /// Save the value of an option of this volume to its options file.
+    ///
+    /// The rest of the file, including comments, is preserved.
+    pub fn save_option(&self, key: &str, value: &str) -> Result<(), Report<OptionsError>> {
+        let path = &self.config_path;
+        let content = read_to_string(path)
+            .change_context(OptionsError::Read)
+            .attach_path(path)?;
+        let is_volumes = content
+            .lines()
+            .any(|line| line.trim_end() == format!("{VOLUMES_KEY}:"));
+        let volume = is_volumes.then_some(self.name.as_str());
+        let content = set_option_line(&content, volume, key, value)
+            .ok_or_else(|| Report::new(OptionsError::Write))
+            .attach_key_value("Volume", &self.name)
+            .attach_path(path)
+            .attach(format!("Add `{key}: {value}` manually"))?;
+        replace_file(path, content.as_bytes())
+            .change_context(OptionsError::Write)
+            .attach_path(path)
+    }
+
+    /// Get the PCR policy used to seal and unseal the TPM object.
+    pub fn get_pcr_policy(&self) -> Result<PcrPolicy, Report<PcrPolicyError>> {
+        let defaults = PcrPolicy::default();
+        let bank = self.pcr_bank.unwrap_or(defaults.bank());
+        let pcrs = self
+            .pcrs
+            .clone()
+            .unwrap_or_else(|| defaults.pcrs().to_vec());
+        PcrPolicy::new(bank, pcrs)
+    }
 }
 
-fn get_default_config_path() -> Result<PathBuf, Report<OptionsError>> {
-    let paths = get_paths()?;
-    trace!(
-        "Found {} options files:\n{}",
-        paths.len(),
-        paths
+/// Read the volumes of an options file.
+///
+/// The file is either a map of named volumes under the `volumes` key, or the options of a
+/// single volume named after the file stem.
+fn read_options_file(path: &Path) -> Result<Vec<Options>, Report<OptionsError>> {
+    trace!(path = %path.display(), "Reading options from path");
+    let file = File::open(path)
+        .change_context(OptionsError::Read)
+        .attach_path(path)?;
+    let value: Value = serde_yaml::from_reader(file)
+        .change_context(OptionsError::Deserialize)
+        .attach_path(path)?;
+    let volumes = match value.get(VOLUMES_KEY) {
+        Some(Value::Mapping(mapping)) => mapping
             .iter()
-            .map(|path| path.display().to_string())
-            .collect::<Vec<_>>()
-            .join("\n")
-    );
-    match paths.len() {
-        0 => return Err(Report::new(OptionsError::NoFile)),
-        1 => {}
-        _ => return Err(Report::new(OptionsError::MultipleFiles)),
-    }
-    Ok(paths
-        .into_iter()
-        .next()
-        .expect("should be at least one options file"))
+            .map(|(key, value)| {
+                let name = key
+                    .as_str()
+                    .ok_or_else(|| Report::new(OptionsError::VolumeName))?;
+                deserialize_volume(value.clone(), name)
+            })
+            .collect::<Result<Vec<_>, _>>(),
+        Some(_) => Err(Report::new(OptionsError::Deserialize)
+            .attach("`volumes` must be a map of volume names to options")),
+        None => {
+            let name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default();
+            deserialize_volume(value, name).map(|options| vec![options])
+        }
+    };
+    let mut volumes = volumes.attach_path(path)?;
+    for options in &mut volumes {
+        options.config_path = path.to_path_buf();
+    }
+    Ok(volumes)
+}
+
+fn deserialize_volume(value: Value, name: &str) -> Result<Options, Report<OptionsError>> {
+    let mut options: Options = serde_yaml::from_value(value)
+        .change_context(OptionsError::Deserialize)
+        .attach_key_value("Volume", name)?;
+    name.clone_into(&mut options.name);
+    Ok(options)
+}
+
+/// Set the value of a top level option, or an option of a named volume, in YAML content.
+///
+/// Returns `None` if the volume can't be found.
+fn set_option_line(content: &str, volume: Option<&str>, key: &str, value: &str) -> Option<String> {
+    let mut lines: Vec<String> = content.lines().map(ToOwned::to_owned).collect();
+    let (start, end, indent) = match volume {
+        None => (0, lines.len(), 0),
+        Some(name) => {
+            let volumes = lines
+                .iter()
+                .position(|line| line.trim_end() == format!("{VOLUMES_KEY}:"))?;
+            let position = lines
+                .iter()
+                .skip(volumes + 1)
+                .position(|line| get_indent(line) > 0 && line.trim() == format!("{name}:"))?;
+            let start = volumes + 1 + position + 1;
+            let parent_indent = get_indent(lines.get(start - 1)?);
+            let length = lines
+                .iter()
+                .skip(start)
+                .position(|line| !is_blank(line) && get_indent(line) <= parent_indent)
+                .unwrap_or(lines.len() - start);
+            let indent = lines
+                .iter()
+                .skip(start)
+                .take(length)
+                .find(|line| !is_blank(line))
+                .map_or(parent_indent + 2, |line| get_indent(line));
+            (start, start + length, indent)
+        }
+    };
+    let prefix = format!("{}{key}:", " ".repeat(indent));
+    let line = format!("{prefix} {value}");
+    let existing = lines
+        .iter()
+        .take(end)
+        .skip(start)
+        .position(|line| line.starts_with(&prefix) && get_indent(line) == indent);
+    match existing {
+        Some(position) => {
+            *lines.get_mut(start + position)? = line;
+        }
+        None if volume.is_some() => lines.insert(start, line),
+        None => lines.push(line),
+    }
+    let mut content = lines.join("\n");
+    content.push('\n');
+    Some(content)
+}
+
+fn get_indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_blank(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn select_volume(
+    volumes: Vec<Options>,
+    name: Option<&str>,
+) -> Result<Options, Report<OptionsError>> {
+    if let Some(name) = name {
+        return volumes
+            .into_iter()
+            .find(|options| options.name == name)
+            .ok_or_else(|| Report::new(OptionsError::VolumeNotFound))
+            .attach_key_value("Volume", name);
+    }
+    match volumes.len() {
+        0 => Err(Report::new(OptionsError::NoVolume)),
+        1 => Ok(volumes
+            .into_iter()
+            .next()
+            .expect("should be exactly one volume")),
+        _ => {
+            let names = volumes
+                .iter()
+                .map(|options| options.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(Report::new(OptionsError::MultipleVolumes).attach_key_value("Volumes", &names))
+        }
+    }
 }
 
 fn get_paths() -> Result<Vec<PathBuf>, Report<OptionsError>> {
     let dir = config_dir()
         .expect("should be able to get config directory")
         .join(APP_NAME);
-    let paths = read_dir(&dir)
+    let mut paths: Vec<PathBuf> = read_dir(&dir)
         .change_context(OptionsError::ReadDir)?
         .filter_map(Result::ok)
         .filter_map(|entry| {
@@ -96,6 +412,7 @@ fn get_paths() -> Result<Vec<PathBuf>, Report<OptionsError>> {
             Some(path)
         })
         .collect();
+    paths.sort();
     Ok(paths)
 }
 
@@ -105,12 +422,22 @@ pub enum OptionsError {
     ReadDir,
     #[error("Options file does not exist")]
     NoFile,
-    #[error("Multiple options files found")]
-    MultipleFiles,
+    #[error("No volumes are configured")]
+    NoVolume,
+    #[error("Multiple volumes found, select one with `--volume`")]
+    MultipleVolumes,
+    #[error("Volume does not exist")]
+    VolumeNotFound,
+    #[error("Volume is configured more than once")]
+    DuplicateVolume,
+    #[error("Volume names must be strings")]
+    VolumeName,
     #[error("Unable to read options file")]
     Read,
     #[error("Unable to deserialize options file")]
     Deserialize,
+    #[error("Unable to write options file")]
+    Write,
 }
 
 #[cfg(test)]
@@ -123,7 +450,7 @@ mod tests {
         assert!(is_root().is_ok(), "Root is required to run this test");
         // Arrange
         // Act
-        let _options = Options::read_options(None).expect("Should be able to read options");
+        let _options = Options::read_options(None, None).expect("Should be able to read options");
 
         // Assert
     }
@@ -146,11 +473,134 @@ mod tests {
         let target_path = paths.get(1).expect("should have path at index 1").clone();
 
         // Act
-        let options = Options::read_options(Some(target_path)).expect("should read options");
+        let options = Options::read_options(Some(target_path), None).expect("should read options");
 
         // Assert
+        assert_eq!(options.name, "config2");
         assert_eq!(options.mapper_name, "test-2");
         assert_eq!(options.partition_path, PathBuf::from("/dev/sda2"));
         assert_eq!(options.mount_path, PathBuf::from("/mnt/test2"));
     }
+
+    #[test]
+    fn read_options_selects_named_volume() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("should create temp directory");
+        let path = dir.join("volumes.yaml");
+        let content = "volumes:\n  \
+            data:\n    partition_path: /dev/sda1\n    mapper_name: data\n    mount_path: /mnt/data\n  \
+            backup:\n    partition_path: /dev/sdb1\n    mapper_name: backup\n    mount_path: /mnt/backup\n";
+        write(&path, content).expect("should write config file");
+
+        // Act
+        let all = Options::read_all(Some(path.clone())).expect("should read all volumes");
+        let options =
+            Options::read_options(Some(path.clone()), Some("backup")).expect("should read volume");
+        let result = Options::read_options(Some(path), None);
+
+        // Assert
+        let names: Vec<_> = all.iter().map(|options| options.name.as_str()).collect();
+        assert_eq!(names, vec!["data", "backup"]);
+        assert_eq!(options.mapper_name, "backup");
+        assert_eq!(options.partition_path, PathBuf::from("/dev/sdb1"));
+        let report = result.expect_err("should require a volume to be selected");
+        assert_eq!(report.current_context(), &OptionsError::MultipleVolumes);
+    }
+
+    #[test]
+    fn read_options_selects_only_volume() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("should create temp directory");
+        let path = dir.join("volumes.yaml");
+        let content = "volumes:\n  \
+            data:\n    partition_path: /dev/sda1\n    mapper_name: data\n    mount_path: /mnt/data\n";
+        write(&path, content).expect("should write config file");
+
+        // Act
+        let options = Options::read_options(Some(path.clone()), None).expect("should read volume");
+        let result = Options::read_options(Some(path), Some("missing"));
+
+        // Assert
+        assert_eq!(options.name, "data");
+        let report = result.expect_err("should not find volume");
+        assert_eq!(report.current_context(), &OptionsError::VolumeNotFound);
+    }
+
+    #[test]
+    fn set_option_line_single_volume() {
+        // Arrange
+        let content = "partition_path: /dev/sda1\n# TPM handle\ntpm_handle: auto # comment\n";
+
+        // Act
+        let replaced = set_option_line(content, None, "tpm_handle", "0x81000001");
+        let appended = set_option_line(content, None, "key_slot", "3");
+
+        // Assert
+        assert_eq!(
+            replaced.as_deref(),
+            Some("partition_path: /dev/sda1\n# TPM handle\ntpm_handle: 0x81000001\n")
+        );
+        assert_eq!(
+            appended.as_deref(),
+            Some(
+                "partition_path: /dev/sda1\n# TPM handle\ntpm_handle: auto # comment\nkey_slot: 3\n"
+            )
+        );
+    }
+
+    #[test]
+    fn set_option_line_named_volume() {
+        // Arrange
+        let content =
+            "volumes:\n  a:\n    mapper_name: a\n    tpm_handle: auto\n  b:\n    mapper_name: b\n";
+
+        // Act
+        let replaced = set_option_line(content, Some("a"), "tpm_handle", "0x81000001");
+        let inserted = set_option_line(content, Some("b"), "tpm_handle", "0x81000002");
+        let missing = set_option_line(content, Some("c"), "tpm_handle", "0x81000003");
+
+        // Assert
+        assert_eq!(
+            replaced.as_deref(),
+            Some(
+                "volumes:\n  a:\n    mapper_name: a\n    tpm_handle: 0x81000001\n  b:\n    mapper_name: b\n"
+            )
+        );
+        assert_eq!(
+            inserted.as_deref(),
+            Some(
+                "volumes:\n  a:\n    mapper_name: a\n    tpm_handle: auto\n  b:\n    tpm_handle: 0x81000002\n    mapper_name: b\n"
+            )
+        );
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn save_option_round_trip() {
+        // Arrange
+        let dir = TempDirectory::default()
+            .create()
+            .expect("should create temp directory");
+        let path = dir.join("volumes.yaml");
+        let content = "volumes:\n  \
+            data:\n    partition_path: /dev/sda1\n    mapper_name: data\n    mount_path: /mnt/data\n    tpm_handle: auto\n";
+        write(&path, content).expect("should write config file");
+        let options = Options::read_options(Some(path.clone()), None).expect("should read options");
+
+        // Act
+        options
+            .save_option("tpm_handle", "0x81000002")
+            .expect("should save option");
+
+        // Assert
+        let options = Options::read_options(Some(path), None).expect("should read options");
+        assert_eq!(
+            options.get_tpm_handle(),
+            Some(PersistentHandle::from_offset(2))
+        );
+    }
 }
@@ -0,0 +1,32 @@
+use crate::prelude::*;
+use std::env::temp_dir;
+use std::fs::{DirBuilder, File};
+use std::io::{ErrorKind, Read};
+use std::os::unix::fs::DirBuilderExt;
+
+/// Number of random names to try before giving up on creating a private directory.
+const ATTEMPTS: usize = 8;
+
+/// Create a temp directory with a random name that only the current user can access.
+///
+/// Creation fails if the path already exists so a directory or symlink planted by another
+/// user is never reused.
+#[allow(clippy::absolute_paths)]
+pub fn create_private_dir() -> Result<PathBuf, std::io::Error> {
+    for _ in 0..ATTEMPTS {
+        let dir = temp_dir().join(format!("{APP_NAME}-{}", get_random_suffix()?));
+        match DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Err(ErrorKind::AlreadyExists.into())
+}
+
+#[allow(clippy::absolute_paths)]
+fn get_random_suffix() -> Result<String, std::io::Error> {
+    let mut bytes = [0_u8; 8];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(hex::encode(bytes))
+}
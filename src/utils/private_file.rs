@@ -0,0 +1,39 @@
+use std::fs::{OpenOptions, metadata, rename};
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+/// Write a file that only the current user can read.
+///
+/// The content is written to a temporary sibling which is then renamed over `path` so an
+/// interrupted write never leaves a partial file behind.
+#[allow(clippy::absolute_paths)]
+pub fn write_private_file(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    write_file_atomic(path, content, 0o600)
+}
+
+/// Replace the content of an existing file, keeping its permissions.
+///
+/// The content is written to a temporary sibling which is then renamed over `path` so an
+/// interrupted write never leaves a partial file behind.
+#[allow(clippy::absolute_paths)]
+pub fn replace_file(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let mode = metadata(path)?.permissions().mode() & 0o7777;
+    write_file_atomic(path, content, mode)
+}
+
+#[allow(clippy::absolute_paths)]
+fn write_file_atomic(path: &Path, content: &[u8], mode: u32) -> Result<(), std::io::Error> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = Path::new(&temp_path);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(temp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    rename(temp_path, path)
+}
@@ -0,0 +1,314 @@
+use crate::prelude::*;
+use nix::sys::mman::{mlock, munlock};
+use nix::unistd::{SysconfVar, sysconf};
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::fmt::Debug;
+use std::io::{ErrorKind, Read};
+use std::num::NonZero;
+use std::ptr::NonNull;
+use std::sync::{LazyLock, PoisonError};
+use zeroize::Zeroize;
+
+/// Number of bytes a secret read with [`Secret::read_from`] starts with if no capacity is given.
+const DEFAULT_READ_CAPACITY: usize = 64;
+
+/// Page size assumed if it can't be read.
+const DEFAULT_PAGE_SIZE: usize = 4096;
+
+/// Size of the pages locked by `mlock`.
+static PAGE_SIZE: LazyLock<usize> = LazyLock::new(|| {
+    sysconf(SysconfVar::PAGE_SIZE)
+        .ok()
+        .flatten()
+        .and_then(|size| usize::try_from(size).ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+});
+
+/// Number of secrets using each locked page, by page address.
+///
+/// `mlock` works on whole pages and doesn't nest, so a page shared by two secrets is only
+/// unlocked once neither of them uses it.
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// Key material that is locked in memory and zeroed when dropped.
+///
+/// The buffer is never grown after it's created as a reallocation would leave a copy behind.
+/// If the memory can't be locked, for example due to `RLIMIT_MEMLOCK`, a warning is logged
+/// and the secret is still zeroed when dropped.
+pub struct Secret {
+    bytes: Vec<u8>,
+    locked: bool,
+}
+
+impl Secret {
+    /// Take ownership of `bytes` and lock them in memory.
+    #[must_use]
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let locked = lock(&bytes);
+        Self { bytes, locked }
+    }
+
+    /// Create a secret of `length` zero bytes to be written with [`Secret::expose_mut`].
+    #[must_use]
+    pub fn zeroed(length: usize) -> Self {
+        Self::new(vec![0; length])
+    }
+
+    /// Read `reader` to the end into a new secret.
+    ///
+    /// The buffer isn't grown if `reader` has no more than `capacity` bytes. Otherwise when the
+    /// buffer is full the bytes are moved to a secret twice the size, so the previous buffer is
+    /// zeroed rather than left behind by a reallocation.
+    #[allow(clippy::absolute_paths)]
+    pub fn read_from(mut reader: impl Read, capacity: usize) -> Result<Self, std::io::Error> {
+        let capacity = capacity.saturating_add(1).max(DEFAULT_READ_CAPACITY);
+        let mut secret = Self::zeroed(capacity);
+        let mut length = 0;
+        loop {
+            if length == secret.len() {
+                let mut grown = Self::zeroed(length * 2);
+                let (start, _) = grown.bytes.split_at_mut(length);
+                start.copy_from_slice(&secret.bytes);
+                secret = grown;
+            }
+            let (_, spare) = secret.bytes.split_at_mut(length);
+            match reader.read(spare) {
+                Ok(0) => break,
+                Ok(read) => length += read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        secret.truncate(length);
+        Ok(secret)
+    }
+
+    /// Concatenate `parts` into a single secret.
+    #[must_use]
+    pub fn concat(parts: &[&[u8]]) -> Self {
+        let length = parts.iter().map(|part| part.len()).sum();
+        let mut secret = Self::new(Vec::with_capacity(length));
+        for part in parts {
+            secret.bytes.extend_from_slice(part);
+        }
+        secret
+    }
+
+    /// Remove leading and trailing ASCII whitespace in place.
+    #[must_use]
+    pub fn trim(mut self) -> Self {
+        let start = self
+            .bytes
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(self.bytes.len());
+        let end = self
+            .bytes
+            .iter()
+            .rposition(|byte| !byte.is_ascii_whitespace())
+            .map_or(start, |index| index + 1);
+        self.bytes.copy_within(start..end, 0);
+        self.bytes.truncate(end - start);
+        self
+    }
+
+    /// Shorten the secret to `length` bytes.
+    ///
+    /// The removed bytes are zeroed when the secret is dropped.
+    pub fn truncate(&mut self, length: usize) {
+        self.bytes.truncate(length);
+    }
+
+    #[must_use]
+    pub fn expose(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    #[must_use]
+    pub fn expose_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value.into_bytes())
+    }
+}
+
+impl AsRef<[u8]> for Secret {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Debug for Secret {
+    #[allow(clippy::absolute_paths)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret([REDACTED; {}])", self.bytes.len())
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+        if self.locked {
+            unlock(&self.bytes);
+        }
+    }
+}
+
+/// Get the allocation of `bytes` including any spare capacity.
+fn get_region(bytes: &Vec<u8>) -> Option<(NonNull<c_void>, usize)> {
+    if bytes.capacity() == 0 {
+        return None;
+    }
+    let pointer = NonNull::new(bytes.as_ptr().cast_mut().cast::<c_void>())?;
+    Some((pointer, bytes.capacity()))
+}
+
+/// Get the addresses of the pages spanned by a region.
+fn get_pages(pointer: NonNull<c_void>, length: usize) -> impl Iterator<Item = usize> {
+    let page_size = *PAGE_SIZE;
+    let start = pointer.addr().get();
+    let end = start + length;
+    (start - start % page_size..end).step_by(page_size)
+}
+
+fn lock(bytes: &Vec<u8>) -> bool {
+    let Some((pointer, length)) = get_region(bytes) else {
+        return false;
+    };
+    let mut pages = LOCKED_PAGES.lock().unwrap_or_else(PoisonError::into_inner);
+    // SAFETY: The region is the allocation of `bytes` which is owned by the secret until it's
+    // unlocked on drop.
+    match unsafe { mlock(pointer, length) } {
+        Ok(()) => {
+            for page in get_pages(pointer, length) {
+                *pages.entry(page).or_default() += 1;
+            }
+            true
+        }
+        Err(error) => {
+            warn!("Unable to lock key material in memory: {error}");
+            false
+        }
+    }
+}
+
+/// Unlock the pages of `bytes` that aren't used by another secret.
+fn unlock(bytes: &Vec<u8>) {
+    let Some((pointer, length)) = get_region(bytes) else {
+        return;
+    };
+    let mut pages = LOCKED_PAGES.lock().unwrap_or_else(PoisonError::into_inner);
+    for page in get_pages(pointer, length) {
+        let Some(count) = pages.get_mut(&page) else {
+            continue;
+        };
+        *count -= 1;
+        if *count > 0 {
+            continue;
+        }
+        pages.remove(&page);
+        let Some(page) = NonZero::new(page).map(|page| pointer.with_addr(page)) else {
+            continue;
+        };
+        // SAFETY: The page was locked by `lock` for the allocation of `bytes` which hasn't been
+        // freed yet, and no other secret uses it.
+        if let Err(error) = unsafe { munlock(page, *PAGE_SIZE) } {
+            debug!("Unable to unlock key material: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_trim() {
+        // Arrange
+        let secret = Secret::from("  key\n".to_owned());
+
+        // Act
+        let secret = secret.trim();
+
+        // Assert
+        assert_eq!(secret.expose(), b"key");
+    }
+
+    #[test]
+    fn secret_trim_whitespace() {
+        // Arrange
+        let secret = Secret::from(" \n".to_owned());
+
+        // Act
+        let secret = secret.trim();
+
+        // Assert
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn secret_concat() {
+        // Arrange
+        // Act
+        let secret = Secret::concat(&[b"a", b"\n", b"bc"]);
+
+        // Assert
+        assert_eq!(secret.expose(), b"a\nbc");
+    }
+
+    #[test]
+    fn secret_read_from() {
+        // Arrange
+        let input = vec![7_u8; DEFAULT_READ_CAPACITY * 3 + 1];
+
+        // Act
+        let secret = Secret::read_from(input.as_slice(), 0).expect("Should be able to read secret");
+
+        // Assert
+        assert_eq!(secret.expose(), input);
+    }
+
+    #[test]
+    fn secret_drop_keeps_shared_pages_locked() {
+        // Arrange
+        let first = Secret::zeroed(16);
+        let second = Secret::zeroed(16);
+
+        // Act
+        drop(first);
+
+        // Assert
+        if second.locked {
+            let (pointer, length) = get_region(&second.bytes).expect("Should have a region");
+            let pages = LOCKED_PAGES.lock().expect("Should be able to lock pages");
+            assert!(get_pages(pointer, length).all(|page| pages.contains_key(&page)));
+        }
+    }
+
+    #[test]
+    fn secret_debug() {
+        // Arrange
+        let secret = Secret::from("password".to_owned());
+
+        // Act
+        let output = format!("{secret:?}");
+
+        // Assert
+        assert!(!output.contains("password"));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use owo_colors::OwoColorize;
+use std::io::{Write, stderr, stdin};
 
 const CHECK: &str = " ✓ ";
 const CROSS: &str = " ⨯ ";
@@ -12,12 +13,20 @@ pub fn print_header(options: &Options, command: SubCommand) {
     ];
     let body = [
         format!("     Command: {command}"),
+        format!("      Volume: {}", options.name),
+        format!("      Config: {}", options.config_path.display()),
         format!("   Partition: {}", options.partition_path.display()),
         format!(" Mapper path: {}", options.get_mapper_path().display()),
         format!("  Mount path: {}", options.mount_path.display()),
         format!("    Key path: {}", display_path_option(&options.key_path)),
+        format!("  Key device: {}", display_option(&options.key_device)),
         format!("  TPM handle: {}", display_option(&options.tpm_handle)),
+        format!("     TPM PIN: {}", display_option(&options.tpm_pin)),
+        format!("  PCR policy: {}", display_result(options.get_pcr_policy())),
         format!("  Key prompt: {}", display_option(&options.key_prompt)),
+        format!("    Tang URL: {}", display_option(&options.tang_url)),
+        format!("  Derivation: {}", display_option(&options.key_derivation)),
+        format!("    Key slot: {}", display_option(&options.key_slot)),
     ];
     eprintln!(
         "{}\n{}\n",
@@ -40,6 +49,48 @@ pub fn print_error(message: &str) {
     error!("{} {message}", CROSS.dimmed());
 }
 
+/// Ask a yes or no question on stderr and read the answer from stdin.
+///
+/// Anything other than `y` or `yes` is treated as no.
+#[allow(clippy::absolute_paths)]
+pub fn prompt_confirm(message: &str) -> Result<bool, std::io::Error> {
+    eprint!("{message} [y/N] ");
+    stderr().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// Print a table to stdout with each column padded to its widest value.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let header = headers
+        .iter()
+        .zip(&widths)
+        .map(|(header, width)| format!("{header:width$}"))
+        .collect::<Vec<_>>()
+        .join("  ");
+    println!("{}", header.trim_end().bold());
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
+
 #[allow(clippy::ref_option)]
 fn display_option<T: Display>(value: &Option<T>) -> String {
     if let Some(value) = value {
@@ -49,6 +100,13 @@ fn display_option<T: Display>(value: &Option<T>) -> String {
     }
 }
 
+fn display_result<T: Display, E>(value: Result<T, E>) -> String {
+    match value {
+        Ok(value) => value.to_string(),
+        Err(_) => "Invalid".italic().to_string(),
+    }
+}
+
 #[allow(clippy::ref_option)]
 fn display_path_option(value: &Option<PathBuf>) -> String {
     if let Some(value) = value {